@@ -0,0 +1,44 @@
+use axum::routing::{get, put};
+use axum::{Extension, Router};
+
+use crate::journal::Journal;
+use crate::mocks::Mocks;
+
+pub mod requests;
+pub mod routes;
+pub mod scenarios;
+
+/// All admin endpoints are served below this prefix. Requests to it are
+/// neither delayed nor recorded in the journal.
+pub const ADMIN_PREFIX: &str = "/__mocked";
+
+pub fn router(journal: Journal, mocks: Mocks) -> Router {
+    let admin = Router::new()
+        .route(
+            "/requests",
+            get(requests::list_requests).delete(requests::clear_requests),
+        )
+        .route(
+            "/routes",
+            get(routes::list_routes)
+                .post(routes::add_route)
+                .delete(routes::clear_routes),
+        )
+        .route(
+            "/routes/{id}",
+            get(routes::get_route)
+                .put(routes::replace_route)
+                .delete(routes::delete_route),
+        )
+        .route(
+            "/scenarios",
+            get(scenarios::list_scenarios).delete(scenarios::reset_scenarios),
+        )
+        .route(
+            "/scenarios/{name}",
+            put(scenarios::set_scenario).delete(scenarios::reset_scenario),
+        )
+        .layer(Extension(journal))
+        .layer(Extension(mocks));
+    Router::new().nest(ADMIN_PREFIX, admin)
+}
@@ -0,0 +1,92 @@
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use crate::journal::{Journal, JournalFilter};
+
+pub async fn list_requests(
+    Extension(journal): Extension<Journal>,
+    Query(filter): Query<JournalFilter>,
+) -> impl IntoResponse {
+    Json(journal.entries(&filter))
+}
+
+pub async fn clear_requests(Extension(journal): Extension<Journal>) -> impl IntoResponse {
+    journal.clear();
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_test::TestServer;
+
+    use crate::journal::{Journal, JournalEntry};
+    use crate::mocks::Mocks;
+    use crate::routing::config::Config;
+    use crate::start::app;
+
+    fn server() -> TestServer {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /v1/login
+                methods:
+                  - POST
+                conditions:
+                  - type: BodyContains
+                    with:
+                      values:
+                        - admin
+                    response:
+                      status: 403
+                  - response:
+                      status: 200
+            "#,
+        )
+        .unwrap();
+        TestServer::new(app(Mocks::new(config).unwrap(), Journal::default())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_recorded() {
+        let server = server();
+        server.post("/v1/login").text(r#"{"user": "foo"}"#).await;
+        server.post("/v1/login").text(r#"{"user": "admin"}"#).await;
+        server.get("/unknown").await;
+
+        let entries: Vec<JournalEntry> = server.get("/__mocked/requests").await.json();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].route.as_deref(), Some("/v1/login"));
+        assert_eq!(entries[0].condition, Some(1));
+        assert_eq!(entries[1].condition, Some(0));
+        assert_eq!(entries[1].status, 403);
+        assert_eq!(entries[2].route, None);
+        assert_eq!(entries[2].status, 404);
+
+        let entries: Vec<JournalEntry> = server
+            .get("/__mocked/requests")
+            .add_query_param("method", "POST")
+            .add_query_param("path", "/v1/login")
+            .add_query_param("body", "foo")
+            .await
+            .json();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].body, r#"{"user": "foo"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_cleared() {
+        let server = server();
+        server.post("/v1/login").await;
+        server
+            .delete("/__mocked/requests")
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+        let entries: Vec<JournalEntry> = server.get("/__mocked/requests").await.json();
+        assert!(entries.is_empty());
+    }
+}
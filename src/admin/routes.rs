@@ -0,0 +1,215 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+
+use crate::mocks::{Mocks, RouteError};
+use crate::routing::route::Route;
+
+/// Routes can be sent as JSON or YAML, as every JSON document is valid YAML.
+fn parse_route(body: &str) -> Result<Route, (StatusCode, String)> {
+    serde_yaml::from_str(body).map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid route: {e}")))
+}
+
+fn rejected(e: RouteError) -> Response {
+    let status = match e {
+        RouteError::Conflict { .. } | RouteError::DuplicateId(_) => StatusCode::CONFLICT,
+        RouteError::DuplicateMethod { .. }
+        | RouteError::OptionsWithCors(_)
+        | RouteError::InvalidPath { .. }
+        | RouteError::Response { .. }
+        | RouteError::SharedTls { .. } => StatusCode::BAD_REQUEST,
+    };
+    (status, e.to_string()).into_response()
+}
+
+pub async fn list_routes(Extension(mocks): Extension<Mocks>) -> impl IntoResponse {
+    Json(mocks.routes())
+}
+
+pub async fn get_route(Extension(mocks): Extension<Mocks>, Path(id): Path<String>) -> Response {
+    match mocks.route(&id) {
+        Some(route) => Json(route).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn add_route(Extension(mocks): Extension<Mocks>, body: String) -> Response {
+    let route = match parse_route(&body) {
+        Ok(route) => route,
+        Err(e) => return e.into_response(),
+    };
+    match mocks.add_route(route) {
+        Ok(route) => {
+            log::info!(
+                "Added route {} @ {}",
+                route.id.as_deref().unwrap_or(""),
+                route.path
+            );
+            (StatusCode::CREATED, Json(route)).into_response()
+        }
+        Err(e) => rejected(e),
+    }
+}
+
+pub async fn replace_route(
+    Extension(mocks): Extension<Mocks>,
+    Path(id): Path<String>,
+    body: String,
+) -> Response {
+    let route = match parse_route(&body) {
+        Ok(route) => route,
+        Err(e) => return e.into_response(),
+    };
+    match mocks.replace_route(&id, route) {
+        Ok(Some(route)) => {
+            log::info!("Replaced route {id} @ {}", route.path);
+            Json(route).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => rejected(e),
+    }
+}
+
+pub async fn delete_route(Extension(mocks): Extension<Mocks>, Path(id): Path<String>) -> Response {
+    match mocks.remove_route(&id) {
+        Some(route) => {
+            log::info!("Removed route {id} @ {}", route.path);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn clear_routes(Extension(mocks): Extension<Mocks>) -> impl IntoResponse {
+    mocks.clear_routes();
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+
+    use crate::journal::Journal;
+    use crate::mocks::Mocks;
+    use crate::routing::config::Config;
+    use crate::routing::route::Route;
+    use crate::start::app;
+
+    fn server() -> TestServer {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes: []
+            "#,
+        )
+        .unwrap();
+        TestServer::new(app(Mocks::new(config).unwrap(), Journal::default())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_are_managed() {
+        let server = server();
+        let resp = server
+            .post("/__mocked/routes")
+            .text(
+                r#"
+                path: /v1/login
+                methods:
+                  - POST
+                conditions:
+                  - response:
+                      status: 200
+                      body: logged in
+                "#,
+            )
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        let route: Route = resp.json();
+        let id = route.id.unwrap();
+        server.post("/v1/login").await.assert_text("logged in");
+
+        let routes: Vec<Route> = server.get("/__mocked/routes").await.json();
+        assert_eq!(routes.len(), 1);
+
+        server
+            .put(&format!("/__mocked/routes/{id}"))
+            .text(r#"{"path": "/v1/login", "methods": ["POST"], "conditions": [{"response": {"status": 401}}]}"#)
+            .await
+            .assert_status_ok();
+        server
+            .post("/v1/login")
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        server
+            .delete(&format!("/__mocked/routes/{id}"))
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server.post("/v1/login").await.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_and_conflicting_routes_are_rejected() {
+        let server = server();
+        let route =
+            r#"{"path": "/a", "methods": ["GET"], "conditions": [{"response": {"status": 200}}]}"#;
+        server
+            .post("/__mocked/routes")
+            .text(route)
+            .await
+            .assert_status(StatusCode::CREATED);
+        server
+            .post("/__mocked/routes")
+            .text(route)
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        server
+            .post("/__mocked/routes")
+            .text("path: /b")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        server
+            .post("/__mocked/routes")
+            .text(r#"{"path": "/c", "methods": ["GET"], "conditions": [{"response": {"status": 1000}}]}"#)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        server.get("/c").await.assert_status_not_found();
+        server
+            .delete("/__mocked/routes/unknown")
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_routes_axum_cannot_serve_are_a_400() {
+        let server = server();
+        server
+            .post("/__mocked/routes")
+            .text(r#"{"path": "/a", "methods": ["GET", "GET"], "conditions": []}"#)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        server
+            .post("/__mocked/routes")
+            .text(
+                r#"{"path": "/a", "methods": ["OPTIONS"], "enable_cors": true, "conditions": []}"#,
+            )
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        let resp = server
+            .post("/__mocked/routes")
+            .text(r#"{"path": "/a", "methods": ["GET"], "conditions": [{"response": {"status": 200}}]}"#)
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        let id = resp.json::<Route>().id.unwrap();
+        server
+            .put(&format!("/__mocked/routes/{id}"))
+            .text(r#"{"path": "/a", "methods": ["GET", "POST", "POST"], "conditions": []}"#)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        server.get("/a").await.assert_status_ok();
+    }
+}
@@ -0,0 +1,122 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use serde::Deserialize;
+
+use crate::mocks::Mocks;
+
+#[derive(Deserialize)]
+pub struct ScenarioState {
+    state: String,
+}
+
+/// Lists the state of every scenario referenced by a route or set at runtime.
+pub async fn list_scenarios(Extension(mocks): Extension<Mocks>) -> impl IntoResponse {
+    let routes = mocks.routes();
+    let names = routes
+        .iter()
+        .flat_map(|r| r.conditions.iter())
+        .filter_map(|c| c.scenario.as_deref());
+    Json(mocks.scenarios().states(names))
+}
+
+pub async fn reset_scenarios(Extension(mocks): Extension<Mocks>) -> impl IntoResponse {
+    mocks.scenarios().reset_all();
+    StatusCode::NO_CONTENT
+}
+
+pub async fn set_scenario(
+    Extension(mocks): Extension<Mocks>,
+    Path(name): Path<String>,
+    Json(body): Json<ScenarioState>,
+) -> impl IntoResponse {
+    mocks.scenarios().set(&name, &body.state);
+    StatusCode::NO_CONTENT
+}
+
+pub async fn reset_scenario(
+    Extension(mocks): Extension<Mocks>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    mocks.scenarios().reset(&name);
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+    use serde_json::json;
+
+    use crate::journal::Journal;
+    use crate::mocks::Mocks;
+    use crate::routing::config::Config;
+    use crate::start::app;
+
+    fn server() -> TestServer {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /order/{id}
+                methods:
+                  - GET
+                conditions:
+                  - scenario: checkout
+                    state: started
+                    response:
+                      status: 200
+                      body: pending
+                  - scenario: checkout
+                    state: paid
+                    response:
+                      status: 200
+                      body: paid
+              - path: /order/{id}/pay
+                methods:
+                  - POST
+                conditions:
+                  - scenario: checkout
+                    response:
+                      status: 204
+                      nextState: paid
+            "#,
+        )
+        .unwrap();
+        TestServer::new(app(Mocks::new(config).unwrap(), Journal::default())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_response_moves_scenario_to_next_state() {
+        let server = server();
+        server.get("/order/1").await.assert_text("pending");
+        server
+            .post("/order/1/pay")
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server.get("/order/1").await.assert_text("paid");
+        let states: HashMap<String, String> = server.get("/__mocked/scenarios").await.json();
+        assert_eq!(states.get("checkout").unwrap(), "paid");
+
+        server.delete("/__mocked/scenarios").await;
+        server.get("/order/1").await.assert_text("pending");
+    }
+
+    #[tokio::test]
+    async fn test_scenario_state_is_set_and_reset() {
+        let server = server();
+        server
+            .put("/__mocked/scenarios/checkout")
+            .json(&json!({"state": "paid"}))
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server.get("/order/1").await.assert_text("paid");
+        server.delete("/__mocked/scenarios/checkout").await;
+        server.get("/order/1").await.assert_text("pending");
+    }
+}
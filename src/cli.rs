@@ -0,0 +1,160 @@
+//! The `mocked` command line.
+
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, Subcommand};
+
+use crate::routing::config::Config;
+use crate::{import, init, record, start, validate};
+
+#[derive(Parser, Debug)]
+#[command(name = "mocked", about = "Serve mock data")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Init {
+        #[arg(long, default_value_t = 3003)]
+        port: u16,
+        #[arg(long, default_value_t=String::from("localhost"))]
+        address: String,
+        #[arg(long, default_value_t = true, action=ArgAction::Set)]
+        enable_cors: bool,
+        #[arg(long, default_value_t = 100)]
+        min_response_delay_ms: u64,
+        #[arg(long, default_value_t = 500)]
+        max_response_delay_ms: u64,
+        #[arg(long, default_value_t=false, action=ArgAction::Set)]
+        without_example_routes: bool,
+        #[arg(long, default_value_t=String::from("mocked.yml"), help = "Written as YAML, JSON or TOML depending on the extension")]
+        path: String,
+    },
+    Start {
+        #[arg(help = "The config file describing the routes")]
+        config: PathBuf,
+        #[arg(long, help = "Reload the config when it or an included file changes")]
+        watch: bool,
+    },
+    #[command(about = "Check a config for mistakes without starting a server")]
+    Validate {
+        #[arg(help = "The config file describing the routes")]
+        config: PathBuf,
+    },
+    #[command(about = "Translate a config between YAML, JSON and TOML")]
+    Convert {
+        #[arg(help = "The config file to translate")]
+        input: PathBuf,
+        #[arg(help = "The translated config file, its extension selects the format")]
+        output: PathBuf,
+    },
+    #[command(about = "Create a config from an API description")]
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    #[command(about = "Forward all requests to an upstream and record them as routes")]
+    Record {
+        #[arg(long, help = "The base URL of the upstream to record")]
+        upstream: String,
+        #[arg(long, default_value_t=String::from("mocked.yml"))]
+        out: String,
+        #[arg(long, default_value_t = 3003)]
+        port: u16,
+        #[arg(long, default_value_t=String::from("localhost"))]
+        address: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportSource {
+    #[command(about = "Create a route for every operation of an OpenAPI 3 spec")]
+    Openapi {
+        #[arg(help = "The OpenAPI spec in YAML or JSON format")]
+        spec: PathBuf,
+        #[arg(long, default_value_t=String::from("mocked.yml"))]
+        out: String,
+        #[arg(long, default_value_t = 3003)]
+        port: u16,
+        #[arg(long, default_value_t=String::from("localhost"))]
+        address: String,
+    },
+}
+
+/// Runs the command given by the arguments of the process.
+pub async fn run() {
+    let args = Cli::parse();
+
+    match args.command {
+        Commands::Start { config, watch } => start::start(config, watch).await,
+        Commands::Validate { config } => {
+            let validation = validate::validate_file(&config);
+            for diagnostic in &validation.diagnostics {
+                eprintln!("{}", diagnostic.report(&config));
+            }
+            if validation.has_errors() {
+                std::process::exit(1);
+            }
+            println!("{} is valid", config.display());
+        }
+        Commands::Convert { input, output } => {
+            let config = match Config::from_file(&input) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Failed to read {}: {e}", input.display());
+                    std::process::exit(1);
+                }
+            };
+            match config.to_file(&output) {
+                Ok(()) => println!("Configuration written to {}", output.display()),
+                Err(e) => {
+                    eprintln!("Failed to write {}: {e}", output.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import {
+            source:
+                ImportSource::Openapi {
+                    spec,
+                    out,
+                    port,
+                    address,
+                },
+        } => match import::openapi::import(&spec, out.as_ref(), address, port) {
+            Ok(()) => println!("Configuration written to {out}"),
+            Err(e) => {
+                eprintln!("Failed to import {}: {e}", spec.display());
+                std::process::exit(1);
+            }
+        },
+        Commands::Record {
+            upstream,
+            out,
+            port,
+            address,
+        } => record::record(upstream, out.into(), address, port).await,
+        Commands::Init {
+            port,
+            address,
+            enable_cors,
+            min_response_delay_ms,
+            max_response_delay_ms,
+            without_example_routes,
+            path,
+        } => {
+            init::init(
+                port,
+                address,
+                enable_cors,
+                min_response_delay_ms,
+                max_response_delay_ms,
+                without_example_routes,
+                path,
+            )
+            .await
+        }
+    }
+}
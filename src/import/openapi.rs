@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+
+use openapiv3::{
+    Components, Example, MediaType, OpenAPI, Operation, ReferenceOr, Response as ApiResponse,
+    Schema, SchemaKind, StatusCode, StringFormat, Type, VariantOrUnknownOrEmpty,
+};
+use serde_json::{json, Value as JsonValue};
+
+use crate::routing::body::Body;
+use crate::routing::condition::Condition;
+use crate::routing::config::Config;
+use crate::routing::matcher::{Matcher, RegexMatcher, RegexTarget};
+use crate::routing::matchers::Matchers;
+use crate::routing::method::Method;
+use crate::routing::options::Options;
+use crate::routing::pattern::Pattern;
+use crate::routing::response::Response;
+use crate::routing::route::Route;
+
+/// Responses other than the default one are selected using this header,
+/// e.g. `Prefer: code=404` or `Prefer: code=200, example=empty`.
+const PREFER_HEADER: &str = "Prefer";
+
+/// Separates preferences within the `Prefer` header.
+const PREFER_SEPARATORS: &str = r"[;,\s]";
+
+/// Recursive schemas are only synthesized up to this depth.
+const MAX_SCHEMA_DEPTH: usize = 8;
+
+pub fn import(spec: &Path, out: &Path, address: String, port: u16) -> io::Result<()> {
+    let spec = fs::read_to_string(spec)?;
+    // JSON specs are valid YAML, too
+    let spec: OpenAPI = serde_yaml::from_str(&spec).map_err(io::Error::other)?;
+    let config = Config {
+        options: Options {
+            address,
+            port,
+            ..Default::default()
+        },
+        routes: routes(&spec),
+        servers: Vec::new(),
+    };
+    config.to_file(out)
+}
+
+pub fn routes(spec: &OpenAPI) -> Vec<Route> {
+    let resolver = Resolver {
+        components: spec.components.as_ref(),
+    };
+    let base_path = base_path(spec);
+    let mut params = HashMap::new();
+    spec.paths
+        .iter()
+        .filter_map(|(path, item)| item.as_item().map(|item| (path, item)))
+        .flat_map(|(path, item)| {
+            item.iter()
+                .map(move |(method, operation)| (path.clone(), method, operation))
+        })
+        .filter_map(|(path, method, operation)| {
+            let Some(method) = method_from_str(method) else {
+                log::warn!("Skipping unsupported method {method} @ {path}");
+                return None;
+            };
+            Some(Route {
+                id: None,
+                path: unify_params(&format!("{base_path}{path}"), &mut params),
+                methods: vec![method],
+                enable_cors: None,
+                proxy: None,
+                delay: None,
+                websocket: None,
+                require_client_cert: false,
+                hosts: Vec::new(),
+                conditions: resolver.conditions(operation),
+            })
+        })
+        .collect()
+}
+
+/// The router cannot tell apart paths that only differ in the names of their
+/// params, e.g. `/pets/{id}` and `/pets/{petId}`. Params keep the first name
+/// seen at their position, `params` maps the positions to them.
+fn unify_params(path: &str, params: &mut HashMap<String, String>) -> String {
+    let mut position = String::new();
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            let param = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}'));
+            let Some(name) = param else {
+                position.push_str(segment);
+                position.push('/');
+                return segment.to_string();
+            };
+            let wildcard = if name.starts_with('*') { "*" } else { "" };
+            position.push_str(&format!("{{{wildcard}}}/"));
+            let unified = params
+                .entry(position.clone())
+                .or_insert_with(|| name.to_string());
+            if unified != name {
+                log::warn!("Renaming {segment} to {{{unified}}} in {path}");
+            }
+            format!("{{{unified}}}")
+        })
+        .collect();
+    segments.join("/")
+}
+
+fn method_from_str(method: &str) -> Option<Method> {
+    match method {
+        "get" => Some(Method::Get),
+        "post" => Some(Method::Post),
+        "put" => Some(Method::Put),
+        "patch" => Some(Method::Patch),
+        "delete" => Some(Method::Delete),
+        "head" => Some(Method::Head),
+        "options" => Some(Method::Options),
+        _ => None,
+    }
+}
+
+/// The path of the first server URL, e.g. `/v1` for `https://example.com/v1`.
+fn base_path(spec: &OpenAPI) -> String {
+    let Some(server) = spec.servers.first() else {
+        return String::new();
+    };
+    let path = match server.url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or(""),
+        None => server.url.as_str(),
+    };
+    path.trim_end_matches('/').to_string()
+}
+
+struct ResponseExample {
+    name: Option<String>,
+    response: Response,
+}
+
+struct Resolver<'a> {
+    components: Option<&'a Components>,
+}
+
+impl<'a> Resolver<'a> {
+    fn component<T>(
+        &self,
+        reference: &str,
+        prefix: &str,
+        items: impl Fn(&'a Components) -> &'a indexmap::IndexMap<String, ReferenceOr<T>>,
+    ) -> Option<&'a T> {
+        let name = reference.strip_prefix(prefix)?;
+        match items(self.components?).get(name)? {
+            ReferenceOr::Item(item) => Some(item),
+            ReferenceOr::Reference { .. } => {
+                log::warn!("Nested reference {reference} is not supported");
+                None
+            }
+        }
+    }
+
+    fn schema(&self, schema: &'a ReferenceOr<Schema>) -> Option<&'a Schema> {
+        match schema {
+            ReferenceOr::Item(schema) => Some(schema),
+            ReferenceOr::Reference { reference } => {
+                self.component(reference, "#/components/schemas/", |c| &c.schemas)
+            }
+        }
+    }
+
+    fn boxed_schema(&self, schema: &'a ReferenceOr<Box<Schema>>) -> Option<&'a Schema> {
+        match schema {
+            ReferenceOr::Item(schema) => Some(schema),
+            ReferenceOr::Reference { reference } => {
+                self.component(reference, "#/components/schemas/", |c| &c.schemas)
+            }
+        }
+    }
+
+    fn response(&self, response: &'a ReferenceOr<ApiResponse>) -> Option<&'a ApiResponse> {
+        match response {
+            ReferenceOr::Item(response) => Some(response),
+            ReferenceOr::Reference { reference } => {
+                self.component(reference, "#/components/responses/", |c| &c.responses)
+            }
+        }
+    }
+
+    fn example(&self, example: &'a ReferenceOr<Example>) -> Option<&'a Example> {
+        match example {
+            ReferenceOr::Item(example) => Some(example),
+            ReferenceOr::Reference { reference } => {
+                self.component(reference, "#/components/examples/", |c| &c.examples)
+            }
+        }
+    }
+
+    /// One condition per documented status and example. The first success
+    /// response is returned by default, all others need to be requested using
+    /// the `Prefer` header.
+    fn conditions(&self, operation: &'a Operation) -> Vec<Condition> {
+        let mut responses: Vec<(u16, &ApiResponse)> = operation
+            .responses
+            .responses
+            .iter()
+            .filter_map(|(status, response)| {
+                let status = match status {
+                    StatusCode::Code(code) => *code,
+                    StatusCode::Range(range) => range * 100,
+                };
+                self.response(response).map(|r| (status, r))
+            })
+            .collect();
+        if responses.is_empty() {
+            if let Some(response) = operation.responses.default.as_ref() {
+                responses.extend(self.response(response).map(|r| (200, r)));
+            }
+        }
+
+        let mut conditions = Vec::new();
+        let mut default = None;
+        for (status, response) in responses {
+            let examples = self.examples(status, response);
+            if examples.len() > 1 {
+                examples.iter().for_each(|example| {
+                    conditions.push(Condition {
+                        matcher: None,
+                        matchers: Some(Matchers::And(vec![
+                            prefer(format!("code={status}")),
+                            prefer(format!(
+                                "example={}",
+                                example.name.clone().unwrap_or_default()
+                            )),
+                        ])),
+                        response: Some(example.response.clone()),
+                        ..Default::default()
+                    })
+                });
+            }
+            let Some(first) = examples.into_iter().next() else {
+                continue;
+            };
+            conditions.push(Condition {
+                matcher: Some(prefer(format!("code={status}"))),
+                matchers: None,
+                response: Some(first.response.clone()),
+                ..Default::default()
+            });
+            let is_success = (200..300).contains(&status);
+            match &default {
+                None => default = Some((is_success, first.response)),
+                Some((false, _)) if is_success => default = Some((is_success, first.response)),
+                _ => {}
+            }
+        }
+        if let Some((_, response)) = default {
+            conditions.push(Condition {
+                matcher: None,
+                matchers: None,
+                response: Some(response),
+                ..Default::default()
+            });
+        }
+        conditions
+    }
+
+    fn examples(&self, status: u16, response: &'a ApiResponse) -> Vec<ResponseExample> {
+        let media = response
+            .content
+            .get("application/json")
+            .map(|media| ("application/json", media))
+            .or_else(|| response.content.first().map(|(t, m)| (t.as_str(), m)));
+        let Some((content_type, media)) = media else {
+            return vec![ResponseExample {
+                name: None,
+                response: Response {
+                    status,
+                    headers: HashMap::new(),
+                    body: None,
+                    ..Default::default()
+                },
+            }];
+        };
+        self.example_values(media)
+            .into_iter()
+            .map(|(name, value)| {
+                let mut headers = HashMap::new();
+                headers.insert(String::from("Content-Type"), content_type.to_string());
+                ResponseExample {
+                    name,
+                    response: Response {
+                        status,
+                        headers,
+                        body: value.map(|v| body(content_type, v)),
+                        ..Default::default()
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn example_values(&self, media: &'a MediaType) -> Vec<(Option<String>, Option<JsonValue>)> {
+        if let Some(example) = &media.example {
+            return vec![(None, Some(example.clone()))];
+        }
+        let examples: Vec<(Option<String>, Option<JsonValue>)> = media
+            .examples
+            .iter()
+            .filter_map(|(name, example)| {
+                self.example(example)
+                    .and_then(|e| e.value.clone())
+                    .map(|value| (Some(name.clone()), Some(value)))
+            })
+            .collect();
+        if !examples.is_empty() {
+            return examples;
+        }
+        let value = media
+            .schema
+            .as_ref()
+            .and_then(|s| self.schema(s))
+            .map(|s| self.synthesize(s, 0));
+        vec![(None, value)]
+    }
+
+    /// Creates an example value from the schema.
+    fn synthesize(&self, schema: &Schema, depth: usize) -> JsonValue {
+        if let Some(example) = &schema.schema_data.example {
+            return example.clone();
+        }
+        if let Some(default) = &schema.schema_data.default {
+            return default.clone();
+        }
+        if depth > MAX_SCHEMA_DEPTH {
+            return JsonValue::Null;
+        }
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::String(string)) => {
+                if let Some(Some(value)) = string.enumeration.first() {
+                    return json!(value);
+                }
+                match &string.format {
+                    VariantOrUnknownOrEmpty::Item(StringFormat::Date) => json!("2024-01-01"),
+                    VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => {
+                        json!("2024-01-01T00:00:00Z")
+                    }
+                    VariantOrUnknownOrEmpty::Unknown(format) => match format.as_str() {
+                        "uuid" => json!("00000000-0000-0000-0000-000000000000"),
+                        "email" => json!("user@example.com"),
+                        "uri" | "url" => json!("https://example.com"),
+                        _ => json!("string"),
+                    },
+                    _ => json!("string"),
+                }
+            }
+            SchemaKind::Type(Type::Number(number)) => match number.enumeration.first() {
+                Some(Some(value)) => json!(value),
+                _ => json!(number.minimum.unwrap_or(0.0)),
+            },
+            SchemaKind::Type(Type::Integer(integer)) => match integer.enumeration.first() {
+                Some(Some(value)) => json!(value),
+                _ => json!(integer.minimum.unwrap_or(0)),
+            },
+            SchemaKind::Type(Type::Boolean(_)) => json!(true),
+            SchemaKind::Type(Type::Object(object)) => {
+                self.synthesize_object(object.properties.iter(), depth)
+            }
+            SchemaKind::Type(Type::Array(array)) => {
+                let item = array
+                    .items
+                    .as_ref()
+                    .and_then(|i| self.boxed_schema(i))
+                    .map(|i| self.synthesize(i, depth + 1));
+                JsonValue::Array(item.into_iter().collect())
+            }
+            SchemaKind::AllOf { all_of } => {
+                let merged = all_of
+                    .iter()
+                    .filter_map(|s| self.schema(s))
+                    .map(|s| self.synthesize(s, depth + 1))
+                    .fold(serde_json::Map::new(), |mut acc, value| {
+                        if let JsonValue::Object(object) = value {
+                            acc.extend(object);
+                        }
+                        acc
+                    });
+                JsonValue::Object(merged)
+            }
+            SchemaKind::OneOf { one_of: schemas } | SchemaKind::AnyOf { any_of: schemas } => {
+                schemas
+                    .first()
+                    .and_then(|s| self.schema(s))
+                    .map(|s| self.synthesize(s, depth + 1))
+                    .unwrap_or(JsonValue::Null)
+            }
+            SchemaKind::Not { .. } => JsonValue::Null,
+            SchemaKind::Any(any) => {
+                if any.properties.is_empty() {
+                    JsonValue::Null
+                } else {
+                    self.synthesize_object(any.properties.iter(), depth)
+                }
+            }
+        }
+    }
+
+    fn synthesize_object<'b>(
+        &self,
+        properties: impl Iterator<Item = (&'b String, &'b ReferenceOr<Box<Schema>>)>,
+        depth: usize,
+    ) -> JsonValue
+    where
+        'a: 'b,
+    {
+        let object = properties
+            .filter_map(|(name, schema)| {
+                let schema = match schema {
+                    ReferenceOr::Item(schema) => Some(schema.as_ref()),
+                    ReferenceOr::Reference { reference } => {
+                        self.component(reference, "#/components/schemas/", |c| &c.schemas)
+                    }
+                }?;
+                Some((name.clone(), self.synthesize(schema, depth + 1)))
+            })
+            .collect();
+        JsonValue::Object(object)
+    }
+}
+
+/// Matches a whole preference, so `example=foo` does not select `example=foobar`.
+fn prefer(preference: String) -> Matcher {
+    let pattern = format!(
+        "(^|{PREFER_SEPARATORS}){}($|{PREFER_SEPARATORS})",
+        regex::escape(&preference)
+    );
+    Matcher::Regex(RegexMatcher {
+        target: RegexTarget::Header,
+        name: Some(String::from(PREFER_HEADER)),
+        pattern: Pattern::new(&pattern).expect("An escaped pattern is always valid"),
+    })
+}
+
+fn body(content_type: &str, value: JsonValue) -> Body {
+    match value {
+        JsonValue::String(value) if !content_type.contains("json") => Body::String(value),
+        value => Body::String(serde_json::to_string_pretty(&value).unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_test::TestServer;
+    use openapiv3::OpenAPI;
+
+    use crate::import::openapi::routes;
+    use crate::mocks::Mocks;
+    use crate::routing::body::Body;
+    use crate::routing::config::Config;
+    use crate::routing::method::Method;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info:
+  title: Pets
+  version: 1.0.0
+servers:
+  - url: https://api.example.com/v1/
+paths:
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: A pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+        "404":
+          description: Not found
+          content:
+            application/json:
+              example:
+                message: not found
+    delete:
+      responses:
+        "204":
+          description: Deleted
+  /pets:
+    get:
+      responses:
+        "200":
+          description: Pets
+          content:
+            application/json:
+              examples:
+                many:
+                  value:
+                    - name: Rex
+                empty:
+                  value: []
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        id:
+          type: integer
+          format: int64
+        name:
+          type: string
+          example: Rex
+        tags:
+          type: array
+          items:
+            type: string
+        status:
+          type: string
+          enum:
+            - available
+            - sold
+"##;
+
+    fn body(body: &Option<Body>) -> serde_json::Value {
+        match body {
+            Some(Body::String(body)) => serde_json::from_str(body).unwrap(),
+            other => panic!("Unexpected body {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_operations_become_routes() {
+        let spec: OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let routes = routes(&spec);
+        let summary: Vec<(String, Method)> = routes
+            .iter()
+            .map(|r| (r.path.clone(), r.methods[0]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (String::from("/v1/pets/{petId}"), Method::Get),
+                (String::from("/v1/pets/{petId}"), Method::Delete),
+                (String::from("/v1/pets"), Method::Get),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bodies_are_synthesized_from_schema() {
+        let spec: OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let routes = routes(&spec);
+        let conditions = &routes[0].conditions;
+        // code=200, code=404 and the default
+        assert_eq!(conditions.len(), 3);
+        let default = conditions.last().unwrap();
+        assert!(default.matcher.is_none() && default.matchers.is_none());
+        assert_eq!(default.response.as_ref().unwrap().status, 200);
+        assert_eq!(
+            body(&default.response.as_ref().unwrap().body),
+            serde_json::json!({"id": 0, "name": "Rex", "tags": ["string"], "status": "available"})
+        );
+        assert_eq!(conditions[1].response.as_ref().unwrap().status, 404);
+        assert_eq!(
+            body(&conditions[1].response.as_ref().unwrap().body),
+            serde_json::json!({"message": "not found"})
+        );
+        let deleted = routes[1].conditions.last().unwrap();
+        assert_eq!(deleted.response.as_ref().unwrap().status, 204);
+        assert!(deleted.response.as_ref().unwrap().body.is_none());
+    }
+
+    #[test]
+    fn test_named_examples_become_conditions() {
+        let spec: OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let routes = routes(&spec);
+        let conditions = &routes[2].conditions;
+        // one per example, code=200 and the default
+        assert_eq!(conditions.len(), 4);
+        assert!(conditions[1].matchers.is_some());
+        assert_eq!(
+            body(&conditions[1].response.as_ref().unwrap().body),
+            serde_json::json!([])
+        );
+        assert_eq!(
+            body(&conditions[3].response.as_ref().unwrap().body),
+            serde_json::json!([{"name": "Rex"}])
+        );
+    }
+
+    fn mocks(spec: &str) -> Mocks {
+        let spec: OpenAPI = serde_yaml::from_str(spec).unwrap();
+        Mocks::new(Config {
+            options: Default::default(),
+            routes: routes(&spec),
+            servers: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_examples_are_selected_by_whole_preference() {
+        let mocks = mocks(SPEC);
+        let server =
+            TestServer::new(axum::Router::new().fallback(move |req| mocks.clone().handle(req)))
+                .unwrap();
+        let prefer = |value: &'static str| server.get("/v1/pets").add_header("Prefer", value);
+        prefer("code=200, example=empty")
+            .await
+            .assert_json(&serde_json::json!([]));
+        prefer("code=200;example=emptyish")
+            .await
+            .assert_json(&serde_json::json!([{"name": "Rex"}]));
+    }
+
+    #[test]
+    fn test_param_names_are_unified() {
+        let spec = r##"
+openapi: 3.0.3
+info:
+  title: Pets
+  version: 1.0.0
+paths:
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: A pet
+  /pets/{id}:
+    delete:
+      responses:
+        "204":
+          description: Deleted
+  /pets/{id}/toys/{toyId}:
+    get:
+      responses:
+        "200":
+          description: A toy
+"##;
+        let paths: Vec<String> = mocks(spec).routes().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/pets/{petId}",
+                "/pets/{petId}",
+                "/pets/{petId}/toys/{toyId}"
+            ]
+        );
+    }
+}
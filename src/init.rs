@@ -8,6 +8,7 @@ use tokio::io::AsyncWriteExt;
 
 use crate::routing::body::{Body, Include};
 use crate::routing::condition::Condition;
+use crate::routing::format::Format;
 use crate::routing::matcher::{
     BodyContainsMatcher, HeaderContainsMatcher, Matcher, PathParamMatcher,
 };
@@ -40,10 +41,21 @@ pub async fn init(
             enable_cors: Some(enable_cors),
             min_response_delay_ms: Some(min_response_delay_ms),
             max_response_delay_ms: Some(max_response_delay_ms),
+            proxy: None,
+            seed: None,
+            tls: None,
+            journal_capacity: None,
         },
         routes,
+        servers: Vec::new(),
+    };
+    let config_str = match Format::from_path(Path::new(&path)).write(&config) {
+        Ok(config_str) => config_str,
+        Err(e) => {
+            eprintln!("Failed to write config {}: {}", path, e);
+            return;
+        }
     };
-    let config_str = serde_yaml::to_string(&config).unwrap();
     match File::create(&path).await {
         Ok(mut file) => {
             if let Err(e) = file.write_all(config_str.as_bytes()).await {
@@ -67,22 +79,36 @@ fn create_example_routes(_config_dir: PathBuf) -> Vec<Route> {
     );
     vec![
         Route {
+            id: None,
+            proxy: None,
+            delay: None,
+            websocket: None,
+            require_client_cert: false,
+            hosts: Vec::new(),
             path: String::from("/hello"),
             methods: vec![Method::Get],
             enable_cors: Some(false),
             conditions: vec![Condition {
                 matcher: None,
                 matchers: None,
-                response: Response {
+                response: Some(Response {
                     status: StatusCode::OK.into(),
                     headers: headers.clone(),
                     body: Some(Body::Include(Include {
                         include: "hello.json".into(),
                     })),
-                },
+                    ..Default::default()
+                }),
+                ..Default::default()
             }],
         },
         Route {
+            id: None,
+            proxy: None,
+            delay: None,
+            websocket: None,
+            require_client_cert: false,
+            hosts: Vec::new(),
             path: String::from("/hello/{name}"),
             methods: vec![Method::Post],
             enable_cors: None,
@@ -91,14 +117,22 @@ fn create_example_routes(_config_dir: PathBuf) -> Vec<Route> {
                     values: vec![String::from("hello")],
                 })),
                 matchers: None,
-                response: Response {
+                response: Some(Response {
                     status: StatusCode::OK.into(),
                     headers: HashMap::new(),
                     body: Some(Body::String(String::from("Hello world"))),
-                },
+                    ..Default::default()
+                }),
+                ..Default::default()
             }],
         },
         Route {
+            id: None,
+            proxy: None,
+            delay: None,
+            websocket: None,
+            require_client_cert: false,
+            hosts: Vec::new(),
             path: String::from("/hello/{name}/{age}"),
             methods: vec![Method::Get],
             conditions: vec![Condition {
@@ -113,15 +147,23 @@ fn create_example_routes(_config_dir: PathBuf) -> Vec<Route> {
                     }),
                 ])),
                 matcher: None,
-                response: Response {
+                response: Some(Response {
                     status: StatusCode::OK.into(),
                     headers,
                     body: None,
-                },
+                    ..Default::default()
+                }),
+                ..Default::default()
             }],
             enable_cors: None,
         },
         Route {
+            id: None,
+            proxy: None,
+            delay: None,
+            websocket: None,
+            require_client_cert: false,
+            hosts: Vec::new(),
             path: String::from("/upload"),
             methods: vec![Method::Post],
             enable_cors: None,
@@ -131,11 +173,13 @@ fn create_example_routes(_config_dir: PathBuf) -> Vec<Route> {
                     values: vec![String::from("Basic"), String::from("Bearer")],
                 })),
                 matchers: None,
-                response: Response {
+                response: Some(Response {
                     status: StatusCode::ACCEPTED.into(),
                     headers: HashMap::new(),
                     body: Some(Body::String(String::from("Accepted"))),
-                },
+                    ..Default::default()
+                }),
+                ..Default::default()
             }],
         },
     ]
@@ -0,0 +1,222 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::extract::{Query, Request};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Attached to a response by the route handler to tell the journal which
+/// route and condition produced it.
+#[derive(Clone, Debug)]
+pub struct MatchedCondition {
+    pub route: String,
+    pub condition: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub query: HashMap<String, String>,
+    pub body: String,
+    pub route: Option<String>,
+    pub condition: Option<usize>,
+    pub status: u16,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct JournalFilter {
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub route: Option<String>,
+    pub status: Option<u16>,
+    pub body: Option<String>,
+}
+
+impl JournalFilter {
+    fn matches(&self, entry: &JournalEntry) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|m| m.eq_ignore_ascii_case(&entry.method))
+            && self.path.as_ref().is_none_or(|p| *p == entry.path)
+            && self
+                .route
+                .as_ref()
+                .is_none_or(|r| Some(r) == entry.route.as_ref())
+            && self.status.is_none_or(|s| s == entry.status)
+            && self.body.as_ref().is_none_or(|b| entry.body.contains(b))
+    }
+}
+
+/// How many requests the journal keeps unless configured otherwise.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// An in-memory record of the most recent requests the server received.
+/// Once `capacity` entries are recorded, the oldest ones are dropped.
+#[derive(Clone, Debug)]
+pub struct Journal {
+    entries: Arc<Mutex<VecDeque<JournalEntry>>>,
+    capacity: usize,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Journal {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Arc::default(),
+            capacity,
+        }
+    }
+
+    pub fn record(&self, entry: JournalEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn entries(&self, filter: &JournalFilter) -> Vec<JournalEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| filter.matches(e))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+fn headers_to_map(headers: &HeaderMap) -> HashMap<String, String> {
+    headers.keys().fold(HashMap::new(), |mut acc, name| {
+        let values = headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        acc.insert(name.to_string(), values);
+        acc
+    })
+}
+
+pub async fn record_request(
+    Extension(journal): Extension<Journal>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let timestamp = Utc::now();
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            log::error!("Failed to read request body - {e}");
+            Default::default()
+        }
+    };
+    let Query(query) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+        .unwrap_or_else(|_| Query(HashMap::new()));
+    let mut entry = JournalEntry {
+        timestamp,
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        headers: headers_to_map(&parts.headers),
+        query,
+        body: String::from_utf8_lossy(&body).to_string(),
+        route: None,
+        condition: None,
+        status: 0,
+    };
+    let resp = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if let Some(matched) = resp.extensions().get::<MatchedCondition>() {
+        entry.route = Some(matched.route.clone());
+        entry.condition = Some(matched.condition);
+    }
+    entry.status = resp.status().as_u16();
+    journal.record(entry);
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use crate::journal::{Journal, JournalEntry, JournalFilter};
+
+    fn entry(method: &str, path: &str, body: &str) -> JournalEntry {
+        JournalEntry {
+            timestamp: Utc::now(),
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: HashMap::new(),
+            query: HashMap::new(),
+            body: body.to_string(),
+            route: None,
+            condition: None,
+            status: 200,
+        }
+    }
+
+    #[test]
+    fn test_entries_are_filtered() {
+        let journal = Journal::default();
+        journal.record(entry("POST", "/v1/login", r#"{"user": "foo"}"#));
+        journal.record(entry("GET", "/v1/login", ""));
+        journal.record(entry("POST", "/v1/login", r#"{"user": "bar"}"#));
+        let filter = JournalFilter {
+            method: Some("post".to_string()),
+            path: Some("/v1/login".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(journal.entries(&filter).len(), 2);
+        let filter = JournalFilter {
+            body: Some("bar".to_string()),
+            ..filter
+        };
+        assert_eq!(journal.entries(&filter).len(), 1);
+        assert_eq!(journal.entries(&JournalFilter::default()).len(), 3);
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let journal = Journal::with_capacity(2);
+        journal.record(entry("GET", "/first", ""));
+        journal.record(entry("GET", "/second", ""));
+        journal.record(entry("GET", "/third", ""));
+        let paths: Vec<String> = journal
+            .entries(&JournalFilter::default())
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, ["/second", "/third"]);
+    }
+
+    #[test]
+    fn test_journal_is_cleared() {
+        let journal = Journal::default();
+        journal.record(entry("GET", "/", ""));
+        journal.clear();
+        assert!(journal.entries(&JournalFilter::default()).is_empty());
+    }
+}
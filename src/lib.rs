@@ -0,0 +1,65 @@
+//! Your friendly HTTP mock response server.
+//!
+//! Besides the `mocked` binary, the crate can be used inside tests. A
+//! [`MockServer`] serves a [`Config`] on a random port:
+//!
+//! ```no_run
+//! # async fn example() {
+//! let config: mocked::Config = serde_yaml::from_str(
+//!     r#"
+//!     options:
+//!       address: localhost
+//!       port: 0
+//!     routes:
+//!       - path: /hello
+//!         methods:
+//!           - GET
+//!         conditions:
+//!           - response:
+//!               status: 200
+//!               body: world
+//!     "#,
+//! )
+//! .unwrap();
+//! let server = mocked::MockServer::start(config).await.unwrap();
+//! let body = reqwest::get(format!("{}/hello", server.url()))
+//!     .await
+//!     .unwrap()
+//!     .text()
+//!     .await
+//!     .unwrap();
+//! assert_eq!(body, "world");
+//! assert_eq!(server.requests().len(), 1);
+//! # }
+//! ```
+
+mod admin;
+pub mod cli;
+mod import;
+mod init;
+mod journal;
+mod mock_server;
+mod mocks;
+mod proxy;
+mod record;
+mod request;
+pub mod routing;
+mod scenarios;
+mod server;
+mod start;
+mod state;
+mod tls;
+mod validate;
+mod watch;
+
+pub use journal::JournalEntry;
+pub use mock_server::{MockServer, MockServerError};
+pub use mocks::RouteError;
+pub use routing::condition::Condition;
+pub use routing::config::Config;
+pub use routing::matcher::Matcher;
+pub use routing::method::Method;
+pub use routing::options::Options;
+pub use routing::response::Response;
+pub use routing::route::Route;
+pub use tls::{Tls, TlsError};
@@ -0,0 +1,211 @@
+use std::net::SocketAddr;
+use std::{fmt, io};
+
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::journal::{Journal, JournalEntry, JournalFilter};
+use crate::mocks::{Mocks, RouteError};
+use crate::routing::config::Config;
+use crate::routing::route::Route;
+use crate::server;
+use crate::start::app;
+use crate::tls::TlsError;
+
+#[derive(Debug)]
+pub enum MockServerError {
+    Io(io::Error),
+    Tls(TlsError),
+    Route(RouteError),
+}
+
+impl fmt::Display for MockServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockServerError::Io(e) => write!(f, "Failed to bind - {e}"),
+            MockServerError::Tls(e) => write!(f, "{e}"),
+            MockServerError::Route(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for MockServerError {}
+
+/// A running mock server for tests. It listens on a random port of the
+/// address of the options, the admin API included, and stops when dropped.
+pub struct MockServer {
+    url: String,
+    addr: SocketAddr,
+    mocks: Mocks,
+    journal: Journal,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: JoinHandle<io::Result<()>>,
+}
+
+impl MockServer {
+    /// Servers on ports of their own are not started, only the routes of the
+    /// options and of servers selected by `hosts` are served.
+    pub async fn start(config: Config) -> Result<MockServer, MockServerError> {
+        let mut listeners = config.listeners().map_err(MockServerError::Route)?;
+        if listeners.len() > 1 {
+            log::warn!("Ignoring servers on ports of their own");
+        }
+        let config = listeners.swap_remove(0);
+        let options = config.options.clone();
+        let hosts = Mocks::hosts(&config);
+        let mocks = Mocks::new(config).map_err(MockServerError::Route)?;
+        let acceptor = match &options.tls {
+            Some(tls) => Some(
+                tls.acceptor(&options.address, &hosts)
+                    .map_err(MockServerError::Tls)?,
+            ),
+            None => None,
+        };
+        let listener = TcpListener::bind((options.address.as_str(), 0))
+            .await
+            .map_err(MockServerError::Io)?;
+        let addr = listener.local_addr().map_err(MockServerError::Io)?;
+        let scheme = if acceptor.is_some() { "https" } else { "http" };
+        let url = format!("{scheme}://{}:{}", options.address, addr.port());
+
+        let journal = options
+            .journal_capacity
+            .map_or_else(Journal::default, Journal::with_capacity);
+        let router = app(mocks.clone(), journal.clone());
+        let (shutdown, stopped) = oneshot::channel();
+        let task = tokio::spawn(server::serve_until(listener, router, acceptor, async {
+            let _ = stopped.await;
+        }));
+        log::info!("Started mock server on {url}");
+        Ok(MockServer {
+            url,
+            addr,
+            mocks,
+            journal,
+            shutdown: Some(shutdown),
+            task,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The base URL of the server, e.g. `http://localhost:38271`.
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn add_route(&self, route: Route) -> Result<Route, RouteError> {
+        self.mocks.add_route(route)
+    }
+
+    pub fn routes(&self) -> Vec<Route> {
+        self.mocks.routes()
+    }
+
+    /// All requests received so far, in order.
+    pub fn requests(&self) -> Vec<JournalEntry> {
+        self.journal.entries(&JournalFilter::default())
+    }
+
+    /// Stops accepting connections, closes the open ones and waits until the
+    /// port is released.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_server::MockServer;
+    use crate::routing::config::Config;
+    use crate::routing::route::Route;
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+            options:
+              address: 127.0.0.1
+              port: 0
+            routes:
+              - path: /hello
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      body: world
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_serves_added_routes_and_records_requests() {
+        let server = MockServer::start(config()).await.unwrap();
+        assert_ne!(server.port(), 0);
+        let resp = reqwest::get(format!("{}/hello", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "world");
+
+        let route: Route = serde_yaml::from_str(
+            r#"
+            path: /added
+            methods:
+              - POST
+            conditions:
+              - response:
+                  status: 201
+            "#,
+        )
+        .unwrap();
+        server.add_route(route).unwrap();
+        let resp = reqwest::Client::new()
+            .post(format!("{}/added", server.url()))
+            .body("payload")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 201);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].path, "/added");
+        assert_eq!(requests[1].body, "payload");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_releases_port() {
+        let server = MockServer::start(config()).await.unwrap();
+        let addr = server.addr();
+        server.shutdown().await;
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_keep_alive_connections() {
+        let server = MockServer::start(config()).await.unwrap();
+        let url = format!("{}/hello", server.url());
+        let client = reqwest::Client::new();
+        let resp = client.get(&url).send().await.unwrap();
+        assert_eq!(resp.text().await.unwrap(), "world");
+        server.shutdown().await;
+        assert!(client.get(&url).send().await.is_err());
+    }
+}
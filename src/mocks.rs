@@ -0,0 +1,630 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{self, Request};
+use axum::http::header::{ALLOW, HOST};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::{middleware, Extension, Router};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::proxy;
+use crate::routing::config::Config;
+use crate::routing::delay::Delay;
+use crate::routing::method::Method;
+use crate::routing::options::Options;
+use crate::routing::response::ResponseError;
+use crate::routing::route::Route;
+use crate::scenarios::Scenarios;
+use crate::state::SharedState;
+
+#[derive(Debug)]
+pub enum RouteError {
+    Conflict {
+        path: String,
+        method: Method,
+    },
+    DuplicateId(String),
+    DuplicateMethod {
+        path: String,
+        method: Method,
+    },
+    /// OPTIONS is answered by CORS already.
+    OptionsWithCors(String),
+    InvalidPath {
+        path: String,
+        reason: String,
+    },
+    Response {
+        path: String,
+        error: ResponseError,
+    },
+    /// A server sets another `tls` than the listener it shares.
+    SharedTls {
+        server: usize,
+        address: String,
+        port: u16,
+    },
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Conflict { path, method } => {
+                write!(f, "A route for {method} {path} already exists")
+            }
+            RouteError::DuplicateId(id) => write!(f, "A route with id {id} already exists"),
+            RouteError::DuplicateMethod { path, method } => {
+                write!(f, "Method {method} is listed twice @ {path}")
+            }
+            RouteError::OptionsWithCors(path) => {
+                write!(f, "OPTIONS cannot be mocked with CORS enabled @ {path}")
+            }
+            RouteError::InvalidPath { path, reason } => write!(f, "Invalid path {path} - {reason}"),
+            RouteError::Response { path, error } => {
+                write!(f, "Invalid response @ {path} - {error}")
+            }
+            RouteError::SharedTls {
+                server,
+                address,
+                port,
+            } => write!(
+                f,
+                "servers[{server}] shares {address}:{port} but sets another tls"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+struct State {
+    config: Config,
+    router: Router,
+    /// Counts the changes, to detect changes made while building a router.
+    version: u64,
+}
+
+/// The currently served config. Its routes can be changed at runtime, every
+/// change builds a new router that is swapped in atomically.
+#[derive(Clone)]
+pub struct Mocks {
+    state: Arc<RwLock<State>>,
+    shared: SharedState,
+}
+
+impl Mocks {
+    pub fn new(config: Config) -> Result<Self, RouteError> {
+        let shared = SharedState::new(config.options.seed);
+        Self::with_shared(config, shared)
+    }
+
+    /// Mocks of several listeners share scenarios and call counts.
+    pub fn with_shared(config: Config, shared: SharedState) -> Result<Self, RouteError> {
+        let config = Self::with_ids(config);
+        let router = Self::build(&config, &shared)?;
+        Ok(Self {
+            state: Arc::new(RwLock::new(State {
+                config,
+                router,
+                version: 0,
+            })),
+            shared,
+        })
+    }
+
+    pub fn scenarios(&self) -> Scenarios {
+        self.shared.scenarios.clone()
+    }
+
+    pub fn routes(&self) -> Vec<Route> {
+        self.state.read().unwrap().config.routes.clone()
+    }
+
+    pub fn route(&self, id: &str) -> Option<Route> {
+        self.routes()
+            .into_iter()
+            .find(|r| r.id.as_deref() == Some(id))
+    }
+
+    pub fn add_route(&self, mut route: Route) -> Result<Route, RouteError> {
+        let id = route
+            .id
+            .get_or_insert_with(|| Uuid::new_v4().to_string())
+            .clone();
+        self.update(|current| {
+            if current.routes.iter().any(|r| r.id.as_ref() == Some(&id)) {
+                return Err(RouteError::DuplicateId(id.clone()));
+            }
+            Self::check_conflicts(current, &route, None)?;
+            let mut config = current.clone();
+            config.routes.push(route.clone());
+            Ok(Some((config, ())))
+        })?;
+        Ok(route)
+    }
+
+    /// Replaces the route with the given id. Returns `None` if there is no such route.
+    pub fn replace_route(&self, id: &str, mut route: Route) -> Result<Option<Route>, RouteError> {
+        route.id = Some(id.to_string());
+        let replaced = self.update(|current| {
+            let Some(index) = Self::position(current, id) else {
+                return Ok(None);
+            };
+            Self::check_conflicts(current, &route, Some(index))?;
+            let mut config = current.clone();
+            config.routes[index] = route.clone();
+            Ok(Some((config, ())))
+        })?;
+        Ok(replaced.map(|()| route))
+    }
+
+    pub fn remove_route(&self, id: &str) -> Option<Route> {
+        let removed = self.update(|current| {
+            let Some(index) = Self::position(current, id) else {
+                return Ok(None);
+            };
+            let mut config = current.clone();
+            let route = config.routes.remove(index);
+            Ok(Some((config, route)))
+        });
+        // fewer routes cannot conflict
+        removed.unwrap_or_else(|e| {
+            log::error!("Unable to remove route {id} - {e}");
+            None
+        })
+    }
+
+    pub fn clear_routes(&self) {
+        let cleared = self.update(|current| {
+            let mut config = current.clone();
+            config.routes.clear();
+            Ok(Some((config, ())))
+        });
+        if let Err(e) = cleared {
+            log::error!("Unable to clear routes - {e}");
+        }
+    }
+
+    /// Replaces the whole config, e.g. after the config file changed. Routes
+    /// added at runtime are dropped.
+    pub fn replace_config(&self, config: Config) -> Result<(), RouteError> {
+        let config = Self::with_ids(config);
+        self.update(|_| Ok(Some((config.clone(), ()))))?;
+        Ok(())
+    }
+
+    /// Checks that no two routes of the config conflict and that all of them
+    /// can be served.
+    pub fn validate(config: &Config) -> Result<(), RouteError> {
+        for (index, route) in config.routes.iter().enumerate() {
+            let previous = Config {
+                routes: config.routes[..index].to_vec(),
+                ..config.clone()
+            };
+            Self::check_conflicts(&previous, route, None)?;
+            config.inherit_enable_cors_for(route.clone()).check()?;
+        }
+        // routes for different hosts end up in different routers
+        Self::check_paths(config.routes.iter().filter(|r| r.hosts.is_empty()))
+            .map_err(|(_, e)| e)?;
+        for host in Self::hosts(config) {
+            Self::check_paths(config.routes.iter().filter(|r| r.serves_host(&host)))
+                .map_err(|(_, e)| e)?;
+        }
+        Ok(())
+    }
+
+    pub fn options(&self) -> Options {
+        self.state.read().unwrap().config.options.clone()
+    }
+
+    /// Dispatches the request to the router that is current at the time of the call.
+    pub async fn handle(self, req: Request) -> axum::response::Response {
+        let router = self.state.read().unwrap().router.clone();
+        let mut resp = router.oneshot(req).await.into_response();
+        if resp.extensions().get::<proxy::WithoutAllow>().is_some() {
+            resp.headers_mut().remove(ALLOW);
+        }
+        resp
+    }
+
+    fn position(config: &Config, id: &str) -> Option<usize> {
+        config
+            .routes
+            .iter()
+            .position(|r| r.id.as_deref() == Some(id))
+    }
+
+    fn check_conflicts(
+        config: &Config,
+        route: &Route,
+        skip: Option<usize>,
+    ) -> Result<(), RouteError> {
+        let route = config.inherit_enable_cors_for(route.clone());
+        config
+            .routes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != skip)
+            .map(|(_, r)| config.inherit_enable_cors_for(r.clone()))
+            .find_map(|r| route.conflicts_with(&r))
+            .map_or(Ok(()), |method| {
+                Err(RouteError::Conflict {
+                    path: route.path.clone(),
+                    method,
+                })
+            })
+    }
+
+    /// axum refuses paths its router cannot tell apart, e.g. `/{id}` and
+    /// `/{name}`. It uses matchit as well, so both agree on what is refused.
+    /// Errors carry the position of the refused route.
+    pub(crate) fn check_paths<'a>(
+        routes: impl IntoIterator<Item = &'a Route>,
+    ) -> Result<(), (usize, RouteError)> {
+        let mut paths = matchit::Router::new();
+        let mut seen = HashSet::new();
+        for (index, route) in routes.into_iter().enumerate() {
+            if !seen.insert(route.path.as_str()) {
+                continue;
+            }
+            paths.insert(route.path.as_str(), ()).map_err(|e| {
+                let error = RouteError::InvalidPath {
+                    path: route.path.clone(),
+                    reason: e.to_string(),
+                };
+                (index, error)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Applies `change` to the current config. The new config is validated
+    /// and its router built before the write lock is taken, so a config that
+    /// cannot be served never reaches the state. `change` returns `None` to
+    /// leave the config as is, and runs again if another change came first.
+    fn update<T>(
+        &self,
+        mut change: impl FnMut(&Config) -> Result<Option<(Config, T)>, RouteError>,
+    ) -> Result<Option<T>, RouteError> {
+        loop {
+            let (version, current) = {
+                let state = self.state.read().unwrap();
+                (state.version, state.config.clone())
+            };
+            let Some((config, value)) = change(&current)? else {
+                return Ok(None);
+            };
+            let router = Self::build(&config, &self.shared)?;
+            let mut state = self.state.write().unwrap();
+            if state.version == version {
+                *state = State {
+                    config,
+                    router,
+                    version: version + 1,
+                };
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    fn with_ids(mut config: Config) -> Config {
+        config.routes.iter_mut().for_each(|r| {
+            r.id.get_or_insert_with(|| Uuid::new_v4().to_string());
+        });
+        config
+    }
+
+    /// Routes for specific hosts get a router of their own. Requests for
+    /// other hosts are answered by the routes without hosts.
+    fn build(config: &Config, shared: &SharedState) -> Result<Router, RouteError> {
+        Self::validate(config)?;
+        let hosts = Self::hosts(config);
+        let routes_for = |filter: &dyn Fn(&Route) -> bool| Config {
+            routes: config
+                .routes
+                .iter()
+                .filter(|r| filter(r))
+                .cloned()
+                .collect(),
+            ..config.clone()
+        };
+        let default = Self::build_for(&routes_for(&|r| r.hosts.is_empty()), shared)?;
+        if hosts.is_empty() {
+            return Ok(default);
+        }
+        let routers: Arc<Vec<(String, Router)>> = Arc::new(
+            hosts
+                .into_iter()
+                .map(|host| {
+                    let router = Self::build_for(&routes_for(&|r| r.serves_host(&host)), shared)?;
+                    Ok((host, router))
+                })
+                .collect::<Result<_, RouteError>>()?,
+        );
+        Ok(Router::new().fallback(move |req: Request| {
+            let router = host(&req)
+                .and_then(|host| routers.iter().find(|(h, _)| h.eq_ignore_ascii_case(host)))
+                .map_or_else(|| default.clone(), |(_, router)| router.clone());
+            router.oneshot(req)
+        }))
+    }
+
+    /// The hosts routes are restricted to, in lower case.
+    pub(crate) fn hosts(config: &Config) -> Vec<String> {
+        let mut hosts: Vec<String> = config
+            .routes
+            .iter()
+            .flat_map(|r| r.hosts.iter())
+            .map(|h| h.to_ascii_lowercase())
+            .collect();
+        hosts.sort();
+        hosts.dedup();
+        hosts
+    }
+
+    fn build_for(config: &Config, shared: &SharedState) -> Result<Router, RouteError> {
+        let options = config.options.clone();
+        let router = config.router(shared)?;
+        let router = match options.proxy.clone() {
+            Some(upstream) => {
+                router.fallback(move |req| proxy::forward_request(upstream.clone(), req))
+            }
+            None => router.fallback(|| async { StatusCode::NOT_FOUND }),
+        };
+        Ok(router
+            .layer(middleware::from_fn_with_state(
+                shared.clone(),
+                delay_response,
+            ))
+            .layer(Extension(options)))
+    }
+}
+
+async fn delay_response(
+    extract::State(shared): extract::State<SharedState>,
+    Extension(options): Extension<Options>,
+    req: Request,
+    next: Next,
+) -> axum::response::Response {
+    if req.method() == axum::http::Method::OPTIONS {
+        return next.run(req).await;
+    }
+    let resp = next.run(req).await;
+    // routes and responses can override the global delay
+    let delay = resp
+        .extensions()
+        .get::<Delay>()
+        .cloned()
+        .unwrap_or_else(|| {
+            let min = options.min_response_delay_ms.unwrap_or(0);
+            let max = options.max_response_delay_ms.unwrap_or(min);
+            Delay::Uniform { min, max }
+        });
+    let delay = delay.sample(&mut *shared.rng());
+    log::info!("Delaying response for: {}ms", delay.as_millis());
+    tokio::time::sleep(delay).await;
+    resp
+}
+
+/// The host of the request without port. HTTP/2 requests carry it in the URI.
+fn host(req: &Request) -> Option<&str> {
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri().host())?;
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => Some(name),
+        _ => Some(host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum_test::TestServer;
+
+    use crate::mocks::{Mocks, RouteError};
+    use crate::routing::config::Config;
+    use crate::routing::route::Route;
+
+    fn mocks() -> Mocks {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - id: hello
+                path: /hello
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      body: hello
+            "#,
+        )
+        .unwrap();
+        Mocks::new(config).unwrap()
+    }
+
+    fn route(path: &str, body: &str) -> Route {
+        serde_yaml::from_str(&format!(
+            r#"
+            path: {path}
+            methods:
+              - GET
+            conditions:
+              - response:
+                  status: 200
+                  body: {body}
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn server(mocks: &Mocks) -> TestServer {
+        let mocks = mocks.clone();
+        let router = axum::Router::new().fallback(move |req| mocks.clone().handle(req));
+        TestServer::new(router).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_added_route_is_served() {
+        let mocks = mocks();
+        let server = server(&mocks);
+        server.get("/added").await.assert_status_not_found();
+        let added = mocks.add_route(route("/added", "added")).unwrap();
+        assert!(added.id.is_some());
+        server.get("/added").await.assert_text("added");
+        server.get("/hello").await.assert_text("hello");
+    }
+
+    #[tokio::test]
+    async fn test_route_is_replaced_and_removed() {
+        let mocks = mocks();
+        let server = server(&mocks);
+        let replaced = mocks.replace_route("hello", route("/hello", "replaced"));
+        assert!(replaced.unwrap().is_some());
+        server.get("/hello").await.assert_text("replaced");
+        assert!(mocks.remove_route("hello").is_some());
+        server.get("/hello").await.assert_status_not_found();
+        assert!(mocks.remove_route("hello").is_none());
+    }
+
+    #[test]
+    fn test_conflicting_route_is_rejected() {
+        let mocks = mocks();
+        let result = mocks.add_route(route("/hello", "other"));
+        assert!(matches!(result, Err(RouteError::Conflict { .. })));
+        assert_eq!(mocks.routes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_route_with_other_param_name_is_rejected_without_breaking_the_mocks() {
+        let mocks = mocks();
+        let server = server(&mocks);
+        mocks.add_route(route("/x/{id}", "id")).unwrap();
+        let mut other = route("/x/{name}", "name");
+        other.methods = vec![crate::routing::method::Method::Post];
+        let result = mocks.add_route(other);
+        assert!(matches!(result, Err(RouteError::InvalidPath { .. })));
+        assert_eq!(mocks.routes().len(), 2);
+        server.get("/x/1").await.assert_text("id");
+        server.get("/hello").await.assert_text("hello");
+    }
+
+    #[test]
+    fn test_config_that_cannot_be_served_is_rejected() {
+        let config = |routes: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                r#"
+                options:
+                  address: localhost
+                  port: 3003
+                routes: {routes}
+                "#
+            ))
+            .unwrap()
+        };
+        let result = Mocks::new(config(
+            r#"[{path: /a, methods: [GET], conditions: [{response: {status: 200, headers: {"Content Type": json}}}]}]"#,
+        ));
+        assert!(matches!(result, Err(RouteError::Response { .. })));
+        let result = Mocks::new(config(
+            "[{path: '/users/{id}', methods: [GET], conditions: []}, {path: '/users/{name}', methods: [POST], conditions: []}]",
+        ));
+        assert!(matches!(result, Err(RouteError::InvalidPath { .. })));
+        let mocks = mocks();
+        let result = mocks.add_route(route("hello", "no-slash"));
+        assert!(matches!(result, Err(RouteError::InvalidPath { .. })));
+        assert_eq!(mocks.routes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_route_and_response_delays_override_global_delay() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+              min_response_delay_ms: 10000
+            routes:
+              - path: /health
+                methods:
+                  - GET
+                delay:
+                  type: fixed
+                  ms: 0
+                conditions:
+                  - response:
+                      status: 200
+              - path: /search
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      delay:
+                        type: uniform
+                        min: 0
+                        max: 10
+            "#,
+        )
+        .unwrap();
+        let server = server(&Mocks::new(config).unwrap());
+        let timeout = Duration::from_secs(1);
+        tokio::time::timeout(timeout, server.get("/health"))
+            .await
+            .unwrap()
+            .assert_status_ok();
+        tokio::time::timeout(timeout, server.get("/search"))
+            .await
+            .unwrap()
+            .assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_routed_by_host() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /me
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      body: default
+              - path: /me
+                methods:
+                  - GET
+                hosts:
+                  - users.local
+                conditions:
+                  - response:
+                      status: 200
+                      body: user
+            "#,
+        )
+        .unwrap();
+        let server = server(&Mocks::new(config).unwrap());
+        let user = server
+            .get("/me")
+            .add_header("host", "Users.local:8000")
+            .await;
+        user.assert_text("user");
+        let other = server.get("/me").add_header("host", "payments.local").await;
+        other.assert_text("default");
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::sync::LazyLock;
+
+use axum::body::{Body, Bytes};
+use axum::extract::Request;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Unable to create HTTP client")
+});
+
+/// Marks a forwarded response that came without `Allow` header. axum adds the
+/// methods of the matched path to it, which do not apply to the upstream.
+#[derive(Clone, Copy, Debug)]
+pub struct WithoutAllow;
+
+/// Headers that only apply to a single connection and must not be forwarded.
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+];
+
+fn strip_hop_by_hop_headers(mut headers: HeaderMap) -> HeaderMap {
+    HOP_BY_HOP_HEADERS.iter().for_each(|name| {
+        headers.remove(*name);
+    });
+    headers
+}
+
+fn upstream_url(upstream: &str, uri: &Uri) -> String {
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    format!("{}{}", upstream.trim_end_matches('/'), path)
+}
+
+/// The upstream could not be reached. Answered with `502 Bad Gateway`.
+#[derive(Debug)]
+pub struct ProxyError {
+    url: String,
+    error: reqwest::Error,
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to proxy request to {}: {}", self.url, self.error)
+    }
+}
+
+impl std::error::Error for ProxyError {}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_GATEWAY, self.to_string()).into_response()
+    }
+}
+
+/// Forwards the request to `upstream` and streams back its response, whatever
+/// its status.
+pub async fn forward(
+    upstream: &str,
+    method: Method,
+    uri: &Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ProxyError> {
+    let url = upstream_url(upstream, uri);
+    log::info!("Proxying {method} {url}");
+    let result = CLIENT
+        .request(method, &url)
+        .headers(strip_hop_by_hop_headers(headers))
+        .body(body)
+        .send()
+        .await;
+    match result {
+        Ok(upstream_resp) => {
+            let status = upstream_resp.status();
+            let headers = strip_hop_by_hop_headers(upstream_resp.headers().clone());
+            let mut resp = Body::from_stream(upstream_resp.bytes_stream()).into_response();
+            *resp.status_mut() = status;
+            *resp.headers_mut() = headers;
+            Ok(resp)
+        }
+        Err(error) => {
+            log::error!("Failed to proxy request to {url} - {error}");
+            Err(ProxyError { url, error })
+        }
+    }
+}
+
+pub async fn forward_request(upstream: String, req: Request) -> Response {
+    let (parts, body) = req.into_parts();
+    match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => forward(&upstream, parts.method, &parts.uri, parts.headers, body)
+            .await
+            .into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to read request body: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Request;
+    use axum::http::StatusCode;
+    use axum::routing::any;
+    use axum::Router;
+    use axum_test::TestServer;
+
+    use crate::mocks::Mocks;
+    use crate::routing::config::Config;
+
+    /// Starts an upstream that echoes method, path and body.
+    async fn upstream() -> String {
+        let router = Router::new().route(
+            "/{*path}",
+            any(|req: Request| async move {
+                let method = req.method().clone();
+                let uri = req.uri().clone();
+                let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    StatusCode::ACCEPTED,
+                    [("x-upstream", "true")],
+                    format!("{method} {uri} {}", String::from_utf8_lossy(&body)),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{address}")
+    }
+
+    fn server(config: &str) -> TestServer {
+        let config: Config = serde_yaml::from_str(config).unwrap();
+        let mocks = Mocks::new(config).unwrap();
+        let router = Router::new().fallback(move |req| mocks.clone().handle(req));
+        TestServer::new(router).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unknown_paths_are_proxied() {
+        let upstream = upstream().await;
+        let server = server(&format!(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+              proxy: {upstream}
+            routes:
+              - path: /mocked
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      body: mocked
+            "#
+        ));
+        server.get("/mocked").await.assert_text("mocked");
+        let resp = server.post("/other?page=1").text("hello").await;
+        resp.assert_status(StatusCode::ACCEPTED);
+        resp.assert_header("x-upstream", "true");
+        resp.assert_text("POST /other?page=1 hello");
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_conditions_are_proxied() {
+        let upstream = upstream().await;
+        let server = server(&format!(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /v1/users/{{id}}
+                methods:
+                  - GET
+                proxy: {upstream}/api
+                conditions:
+                  - type: PathParam
+                    with:
+                      name: id
+                      values:
+                        - 1
+                    response:
+                      status: 200
+                      body: mocked
+            "#
+        ));
+        server.get("/v1/users/1").await.assert_text("mocked");
+        server
+            .get("/v1/users/2")
+            .await
+            .assert_text("GET /api/v1/users/2 ");
+        server.get("/unknown").await.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_methods_without_route_are_proxied() {
+        let upstream = upstream().await;
+        let config = |proxy: &str| {
+            format!(
+                r#"
+                options:
+                  address: localhost
+                  port: 3003
+                  {proxy}
+                routes:
+                  - path: /v1/users/{{id}}
+                    methods:
+                      - GET
+                    conditions:
+                      - response:
+                          status: 200
+                          body: mocked
+                  - path: /v1/orders
+                    methods:
+                      - GET
+                    proxy: {upstream}/api
+                    conditions: []
+                "#
+            )
+        };
+        let proxied = server(&config(&format!("proxy: {upstream}")));
+        proxied.get("/v1/users/1").await.assert_text("mocked");
+        let resp = proxied.post("/v1/users/1").text("hello").await;
+        resp.assert_status(StatusCode::ACCEPTED);
+        resp.assert_text("POST /v1/users/1 hello");
+        assert!(resp.headers().get("allow").is_none());
+        proxied
+            .delete("/v1/orders")
+            .await
+            .assert_text("DELETE /api/v1/orders ");
+
+        let resp = server(&config("")).post("/v1/users/1").await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "GET,HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream_is_bad_gateway() {
+        let server = server(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+              proxy: http://127.0.0.1:1
+            routes: []
+            "#,
+        );
+        server
+            .get("/unknown")
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use axum::body::{Body as AxumBody, Bytes};
+use axum::extract::{Query, Request};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use axum::{Extension, Router};
+
+use crate::proxy;
+use crate::routing::body::{Body, Include};
+use crate::routing::condition::Condition;
+use crate::routing::config::Config;
+use crate::routing::matcher::{BodyContainsMatcher, Matcher, RegexMatcher, RegexTarget};
+use crate::routing::matchers::Matchers;
+use crate::routing::method::Method;
+use crate::routing::options::Options;
+use crate::routing::pattern::Pattern;
+use crate::routing::response::Response;
+use crate::routing::route::Route;
+
+/// Bodies larger than this or that are not valid UTF-8 are written to files.
+const MAX_INLINE_BODY: usize = 4096;
+
+/// Response headers that are specific to a single response.
+const SKIPPED_HEADERS: [&str; 3] = ["content-length", "date", "transfer-encoding"];
+
+pub struct Recorder {
+    out: PathBuf,
+    options: Options,
+    routes: Vec<Route>,
+    seen: HashSet<String>,
+    bodies: usize,
+}
+
+impl Recorder {
+    pub fn new(out: PathBuf, options: Options) -> Self {
+        Self {
+            out,
+            options,
+            routes: Vec::new(),
+            seen: HashSet::new(),
+            bodies: 0,
+        }
+    }
+
+    /// Adds a request/response pair. Returns false if an equal request was
+    /// recorded before.
+    pub fn add(
+        &mut self,
+        method: Method,
+        uri: &Uri,
+        body: &Bytes,
+        status: StatusCode,
+        headers: &HeaderMap,
+        resp_body: &Bytes,
+    ) -> io::Result<bool> {
+        let key = format!("{method} {uri} {}", String::from_utf8_lossy(body));
+        if !self.seen.insert(key) {
+            return Ok(false);
+        }
+        let condition = Condition {
+            matcher: None,
+            matchers: Self::matchers(uri, body),
+            response: Some(Response {
+                status: status.as_u16(),
+                headers: Self::headers(headers),
+                body: self.body(method, uri, headers, resp_body)?,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let path = uri.path().to_string();
+        match self
+            .routes
+            .iter_mut()
+            .find(|r| r.path == path && r.methods.contains(&method))
+        {
+            Some(route) => {
+                route.conditions.push(condition);
+                // conditions without matchers are catch-alls and must come last
+                route.conditions.sort_by_key(|c| c.matchers.is_none());
+            }
+            None => self.routes.push(Route {
+                id: None,
+                path,
+                methods: vec![method],
+                enable_cors: None,
+                proxy: None,
+                delay: None,
+                websocket: None,
+                require_client_cert: false,
+                hosts: Vec::new(),
+                conditions: vec![condition],
+            }),
+        }
+        Ok(true)
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        let config = Config {
+            options: self.options.clone(),
+            routes: self.routes.clone(),
+            servers: Vec::new(),
+        };
+        config.to_file(&self.out)
+    }
+
+    fn matchers(uri: &Uri, body: &Bytes) -> Option<Matchers> {
+        let Query(query) = Query::<HashMap<String, String>>::try_from_uri(uri)
+            .unwrap_or_else(|_| Query(HashMap::new()));
+        let mut query: Vec<(String, String)> = query.into_iter().collect();
+        query.sort();
+        let mut matchers: Vec<Matcher> = query
+            .into_iter()
+            .map(|(name, value)| {
+                Matcher::Regex(RegexMatcher {
+                    target: RegexTarget::Query,
+                    name: Some(name),
+                    pattern: Pattern::new(&format!("^{}$", regex::escape(&value)))
+                        .expect("An escaped pattern is always valid"),
+                })
+            })
+            .collect();
+        if !body.is_empty() {
+            matchers.push(Matcher::BodyContains(BodyContainsMatcher {
+                values: vec![String::from_utf8_lossy(body).to_string()],
+            }));
+        }
+        if matchers.is_empty() {
+            None
+        } else {
+            Some(Matchers::And(matchers))
+        }
+    }
+
+    fn headers(headers: &HeaderMap) -> HashMap<String, String> {
+        headers
+            .iter()
+            .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.to_string(), v.to_string()))
+            })
+            .collect()
+    }
+
+    fn body(
+        &mut self,
+        method: Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> io::Result<Option<Body>> {
+        if body.is_empty() {
+            return Ok(None);
+        }
+        if body.len() <= MAX_INLINE_BODY {
+            if let Ok(body) = String::from_utf8(body.to_vec()) {
+                return Ok(Some(Body::String(body)));
+            }
+        }
+        self.bodies += 1;
+        let include = self.bodies_dir().join(format!(
+            "{:03}_{}_{}.{}",
+            self.bodies,
+            method.to_string().to_lowercase(),
+            sanitize(uri.path()),
+            extension(headers)
+        ));
+        fs::create_dir_all(self.bodies_dir())?;
+        fs::write(&include, body)?;
+        Ok(Some(Body::Include(Include { include })))
+    }
+
+    /// Included files are resolved relative to the working directory, just
+    /// like the output file.
+    fn bodies_dir(&self) -> PathBuf {
+        let stem = self
+            .out
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("mocked"));
+        self.out
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(format!("{stem}_bodies"))
+    }
+}
+
+fn sanitize(path: &str) -> String {
+    let sanitized: String = path
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() {
+        String::from("root")
+    } else {
+        sanitized
+    }
+}
+
+fn extension(headers: &HeaderMap) -> &'static str {
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if content_type.contains("json") {
+        "json"
+    } else if content_type.contains("html") {
+        "html"
+    } else if content_type.contains("xml") {
+        "xml"
+    } else if content_type.starts_with("text/") {
+        "txt"
+    } else {
+        "bin"
+    }
+}
+
+async fn handler(
+    Extension(upstream): Extension<Arc<String>>,
+    Extension(recorder): Extension<Arc<Mutex<Recorder>>>,
+    req: Request,
+) -> AxumResponse {
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Failed to read request body: {e}"),
+            )
+                .into_response()
+        }
+    };
+    let resp = match proxy::forward(
+        &upstream,
+        parts.method.clone(),
+        &parts.uri,
+        parts.headers,
+        body.clone(),
+    )
+    .await
+    {
+        Ok(resp) => resp,
+        // there is nothing to record if the upstream cannot be reached
+        Err(e) => return e.into_response(),
+    };
+    let (resp_parts, resp_body) = resp.into_parts();
+    let resp_body = match axum::body::to_bytes(resp_body, usize::MAX).await {
+        Ok(resp_body) => resp_body,
+        Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read upstream response: {e}"),
+            )
+                .into_response()
+        }
+    };
+    match Method::try_from(&parts.method) {
+        Ok(method) => {
+            let mut recorder = recorder.lock().unwrap();
+            let recorded = recorder.add(
+                method,
+                &parts.uri,
+                &body,
+                resp_parts.status,
+                &resp_parts.headers,
+                &resp_body,
+            );
+            match recorded.and_then(|added| {
+                if added {
+                    recorder.write().map(|_| true)
+                } else {
+                    Ok(false)
+                }
+            }) {
+                Ok(true) => log::info!("Recorded {} {}", parts.method, parts.uri),
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to record {} {} - {e}", parts.method, parts.uri),
+            }
+        }
+        Err(e) => log::warn!("{e}, not recording {}", parts.uri),
+    }
+    AxumResponse::from_parts(resp_parts, AxumBody::from(resp_body))
+}
+
+fn app(upstream: String, recorder: Arc<Mutex<Recorder>>) -> Router {
+    Router::new()
+        .fallback(handler)
+        .layer(Extension(recorder))
+        .layer(Extension(Arc::new(upstream)))
+}
+
+pub async fn record(upstream: String, out: PathBuf, address: String, port: u16) {
+    let options = Options {
+        address: address.clone(),
+        port,
+        ..Default::default()
+    };
+    let recorder = Arc::new(Mutex::new(Recorder::new(out.clone(), options)));
+    let router = app(upstream.clone(), recorder);
+    match tokio::net::TcpListener::bind(format!("{address}:{port}")).await {
+        Ok(listener) => {
+            log::info!(
+                "Recording {upstream} on {address}:{port} into {}",
+                out.display()
+            );
+            if let Err(e) = axum::serve(listener, router).await {
+                log::error!("Failed to start server - {e}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to bind to: {address}:{port} - {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::body::Bytes;
+    use axum::http::{HeaderMap, StatusCode, Uri};
+    use axum::routing::get;
+    use axum::Router;
+    use axum_test::TestServer;
+
+    use crate::record::{app, Recorder};
+    use crate::routing::body::Body;
+    use crate::routing::config::Config;
+    use crate::routing::method::Method;
+    use crate::routing::options::Options;
+
+    fn recorder(dir: &tempfile::TempDir) -> Recorder {
+        Recorder::new(dir.path().join("mocked.yml"), Options::default())
+    }
+
+    fn json_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "application/json".parse().unwrap());
+        headers.insert("date", "Sun, 18 Oct 2026 10:00:00 GMT".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_pairs_are_written_as_loadable_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = recorder(&dir);
+        let uri: Uri = "/v1/search?q=foo".parse().unwrap();
+        let added = recorder
+            .add(
+                Method::Get,
+                &uri,
+                &Bytes::new(),
+                StatusCode::OK,
+                &json_headers(),
+                &Bytes::from(r#"{"hits": 1}"#),
+            )
+            .unwrap();
+        assert!(added);
+        let uri: Uri = "/v1/search".parse().unwrap();
+        recorder
+            .add(
+                Method::Get,
+                &uri,
+                &Bytes::new(),
+                StatusCode::OK,
+                &json_headers(),
+                &Bytes::from(r#"{"hits": 0}"#),
+            )
+            .unwrap();
+        let duplicate = recorder
+            .add(
+                Method::Get,
+                &uri,
+                &Bytes::new(),
+                StatusCode::OK,
+                &json_headers(),
+                &Bytes::from(r#"{"hits": 2}"#),
+            )
+            .unwrap();
+        assert!(!duplicate);
+        recorder.write().unwrap();
+
+        let config = Config::from_file(&dir.path().join("mocked.yml")).unwrap();
+        assert_eq!(config.routes.len(), 1);
+        let conditions = &config.routes[0].conditions;
+        assert_eq!(conditions.len(), 2);
+        assert!(conditions[0].matchers.is_some());
+        assert!(conditions[1].matchers.is_none());
+        let response = conditions[0].response.as_ref().unwrap();
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "application/json"
+        );
+        assert!(!response.headers.contains_key("date"));
+        assert!(matches!(&response.body, Some(Body::String(b)) if b == r#"{"hits": 1}"#));
+    }
+
+    #[test]
+    fn test_binary_bodies_are_included() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = recorder(&dir);
+        let uri: Uri = "/image".parse().unwrap();
+        recorder
+            .add(
+                Method::Get,
+                &uri,
+                &Bytes::new(),
+                StatusCode::OK,
+                &HeaderMap::new(),
+                &Bytes::from_static(&[0xff, 0x00, 0xfe]),
+            )
+            .unwrap();
+        recorder.write().unwrap();
+        let config = Config::from_file(&dir.path().join("mocked.yml")).unwrap();
+        let Some(Body::Include(include)) = &config.routes[0].conditions[0]
+            .response
+            .as_ref()
+            .unwrap()
+            .body
+        else {
+            panic!("Expected an include body");
+        };
+        assert_eq!(
+            include.include,
+            dir.path().join("mocked_bodies").join("001_get_image.bin")
+        );
+        assert_eq!(
+            std::fs::read(&include.include).unwrap(),
+            vec![0xff, 0x00, 0xfe]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upstream_errors_are_recorded_unlike_unreachable_upstreams() {
+        let upstream = Router::new().route(
+            "/down",
+            get(|| async { (StatusCode::BAD_GATEWAY, "maintenance") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Arc::new(Mutex::new(recorder(&dir)));
+
+        let server = TestServer::new(app(format!("http://{address}"), recorder.clone())).unwrap();
+        let resp = server.get("/down").await;
+        resp.assert_status(StatusCode::BAD_GATEWAY);
+        resp.assert_text("maintenance");
+        let config = Config::from_file(&dir.path().join("mocked.yml")).unwrap();
+        assert_eq!(config.routes.len(), 1);
+        let response = config.routes[0].conditions[0].response.as_ref().unwrap();
+        assert_eq!(response.status, 502);
+
+        let unreachable = app(String::from("http://127.0.0.1:1"), recorder);
+        let server = TestServer::new(unreachable).unwrap();
+        server
+            .get("/other")
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+        let config = Config::from_file(&dir.path().join("mocked.yml")).unwrap();
+        assert_eq!(config.routes.len(), 1);
+    }
+}
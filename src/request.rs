@@ -2,10 +2,13 @@ use std::collections::HashMap;
 
 use axum::http::HeaderMap;
 
+use crate::tls::ClientCert;
+
 #[derive(Clone, Debug)]
 pub struct Request {
     pub headers: HeaderMap,
     pub path_params: HashMap<String, String>,
     pub query: HashMap<String, String>,
     pub body: String,
+    pub client_cert: Option<ClientCert>,
 }
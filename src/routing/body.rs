@@ -3,12 +3,15 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use crate::routing::events::Events;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum Body {
     Bytes(Vec<u8>),
     String(String),
     Include(Include),
+    Events(Events),
 }
 
 impl Body {
@@ -25,6 +28,7 @@ impl TryInto<Vec<u8>> for Body {
             Body::Bytes(value) => Ok(value),
             Body::String(value) => Ok(value.as_bytes().to_vec()),
             Body::Include(include) => fs::read(include.include),
+            Body::Events(events) => Ok(events.to_bytes()),
         }
     }
 }
@@ -53,7 +57,10 @@ mod tests {
     #[test]
     fn test_include_is_read_correctly() {
         let tmp_file = tempfile::NamedTempFile::new().unwrap();
-        tmp_file.as_file().write("test-data".as_bytes()).unwrap();
+        tmp_file
+            .as_file()
+            .write_all("test-data".as_bytes())
+            .unwrap();
         let body = Body::Include(Include {
             include: tmp_file.path().to_path_buf(),
         });
@@ -0,0 +1,427 @@
+//! A fluent way to build routes in code. The builders produce the same types
+//! a config file is parsed into, so a config built in code can be dumped as
+//! YAML.
+//!
+//! ```
+//! use mocked::routing::builder::{header, json_path};
+//! use mocked::Route;
+//!
+//! let route = Route::post("/v1/login")
+//!     .when(header("Authorization").contains("Basic"))
+//!     .respond(200)
+//!     .json(serde_json::json!({"token": "abc"}))
+//!     .when(json_path("$.user").missing())
+//!     .respond(400);
+//! assert_eq!(route.conditions.len(), 2);
+//! ```
+//!
+//! Matchers given invalid regular expressions or JSONPaths panic, as they are
+//! meant to be written by hand.
+
+use serde_json_path::JsonPath;
+
+use crate::routing::body::Body;
+use crate::routing::condition::Condition;
+use crate::routing::config::Config;
+use crate::routing::delay::Delay;
+use crate::routing::matcher::{
+    BodyContainsMatcher, BodyJsonPathMatcher, HeaderContainsMatcher, Matcher, PathParamMatcher,
+    QueryContainsMatcher, RegexMatcher, RegexTarget,
+};
+use crate::routing::matchers::Matchers;
+use crate::routing::method::Method;
+use crate::routing::options::Options;
+use crate::routing::pattern::Pattern;
+use crate::routing::response::Response;
+use crate::routing::route::Route;
+use crate::routing::value::Value;
+
+impl Route {
+    pub fn new(method: Method, path: impl Into<String>) -> Route {
+        Route {
+            id: None,
+            path: path.into(),
+            methods: vec![method],
+            enable_cors: None,
+            proxy: None,
+            delay: None,
+            websocket: None,
+            require_client_cert: false,
+            hosts: Vec::new(),
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn get(path: impl Into<String>) -> Route {
+        Route::new(Method::Get, path)
+    }
+
+    pub fn post(path: impl Into<String>) -> Route {
+        Route::new(Method::Post, path)
+    }
+
+    pub fn put(path: impl Into<String>) -> Route {
+        Route::new(Method::Put, path)
+    }
+
+    pub fn patch(path: impl Into<String>) -> Route {
+        Route::new(Method::Patch, path)
+    }
+
+    pub fn delete(path: impl Into<String>) -> Route {
+        Route::new(Method::Delete, path)
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Route {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Adds a method the route responds to.
+    pub fn method(mut self, method: Method) -> Route {
+        self.methods.push(method);
+        self
+    }
+
+    /// Starts a new condition, or adds the matcher to the condition that has
+    /// no response yet. All matchers of a condition have to match.
+    ///
+    /// # Panics
+    ///
+    /// If the open condition was started with [`Route::when_any`].
+    pub fn when(mut self, matcher: Matcher) -> Route {
+        let condition = self.open_condition();
+        match (condition.matcher.take(), &mut condition.matchers) {
+            (None, None) => condition.matcher = Some(matcher),
+            (Some(first), None) => condition.matchers = Some(Matchers::And(vec![first, matcher])),
+            (None, Some(Matchers::And(matchers))) => matchers.push(matcher),
+            (_, Some(Matchers::Or(_))) => panic!("when_any cannot be combined with when"),
+            (Some(_), Some(_)) => unreachable!("conditions of the builder have either"),
+        }
+        self
+    }
+
+    /// Starts a new condition that matches if any of the matchers does.
+    pub fn when_any(mut self, matchers: Vec<Matcher>) -> Route {
+        self.conditions.push(Condition {
+            matchers: Some(Matchers::Or(matchers)),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Only matches while the scenario is in the given state.
+    pub fn in_state(mut self, scenario: impl Into<String>, state: impl Into<String>) -> Route {
+        let condition = self.open_condition();
+        condition.scenario = Some(scenario.into());
+        condition.state = Some(state.into());
+        self
+    }
+
+    /// Sets the response of the open condition. Without one, a condition that
+    /// matches every request is added.
+    pub fn respond(mut self, status: u16) -> Route {
+        self.open_condition().response = Some(Response {
+            status,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Route {
+        self.response().headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Route {
+        self.response().body = Some(Body::String(body.into()));
+        self
+    }
+
+    /// Sets the body and the `Content-Type` of the last response.
+    pub fn json(self, body: serde_json::Value) -> Route {
+        self.header("Content-Type", "application/json")
+            .body(body.to_string())
+    }
+
+    pub fn next_state(mut self, state: impl Into<String>) -> Route {
+        self.response().next_state = Some(state.into());
+        self
+    }
+
+    /// Delays the last response. Use the `delay` field to delay the whole route.
+    pub fn delay(mut self, delay: Delay) -> Route {
+        self.response().delay = Some(delay);
+        self
+    }
+
+    /// The last condition, unless it already has a response.
+    fn open_condition(&mut self) -> &mut Condition {
+        let open = self
+            .conditions
+            .last()
+            .is_some_and(|c| c.response.is_none() && c.responses.is_empty());
+        if !open {
+            self.conditions.push(Condition::default());
+        }
+        self.conditions.last_mut().unwrap()
+    }
+
+    /// The response of the last condition, answering 200 if none was set.
+    fn response(&mut self) -> &mut Response {
+        if self.conditions.is_empty() {
+            self.conditions.push(Condition::default());
+        }
+        let condition = self.conditions.last_mut().unwrap();
+        condition.response.get_or_insert_with(Response::default)
+    }
+}
+
+impl Config {
+    pub fn new(options: Options) -> Config {
+        Config {
+            options,
+            routes: Vec::new(),
+            servers: Vec::new(),
+        }
+    }
+
+    pub fn route(mut self, route: Route) -> Config {
+        self.routes.push(route);
+        self
+    }
+}
+
+fn pattern(pattern: &str) -> Pattern {
+    Pattern::new(pattern).unwrap_or_else(|e| panic!("Invalid pattern {pattern} - {e}"))
+}
+
+fn regex(target: RegexTarget, name: Option<String>, value: &str) -> Matcher {
+    Matcher::Regex(RegexMatcher {
+        target,
+        name,
+        pattern: pattern(value),
+    })
+}
+
+pub struct PathParamBuilder(String);
+
+pub fn path_param(name: impl Into<String>) -> PathParamBuilder {
+    PathParamBuilder(name.into())
+}
+
+impl PathParamBuilder {
+    pub fn equals(self, value: impl Into<Value>) -> Matcher {
+        self.one_of([value])
+    }
+
+    pub fn one_of<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Matcher {
+        Matcher::PathParam(PathParamMatcher {
+            name: self.0,
+            values: values.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub fn matches(self, pattern: &str) -> Matcher {
+        regex(RegexTarget::Path, Some(self.0), pattern)
+    }
+}
+
+pub struct HeaderBuilder(String);
+
+pub fn header(name: impl Into<String>) -> HeaderBuilder {
+    HeaderBuilder(name.into())
+}
+
+impl HeaderBuilder {
+    pub fn contains(self, value: impl Into<String>) -> Matcher {
+        self.contains_any([value])
+    }
+
+    pub fn contains_any<V: Into<String>>(self, values: impl IntoIterator<Item = V>) -> Matcher {
+        Matcher::HeaderContains(HeaderContainsMatcher {
+            name: self.0,
+            values: values.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub fn matches(self, pattern: &str) -> Matcher {
+        regex(RegexTarget::Header, Some(self.0), pattern)
+    }
+}
+
+pub struct QueryBuilder(String);
+
+pub fn query(name: impl Into<String>) -> QueryBuilder {
+    QueryBuilder(name.into())
+}
+
+impl QueryBuilder {
+    pub fn contains(self, value: impl Into<String>) -> Matcher {
+        self.contains_any([value])
+    }
+
+    pub fn contains_any<V: Into<String>>(self, values: impl IntoIterator<Item = V>) -> Matcher {
+        Matcher::QueryContains(QueryContainsMatcher {
+            name: self.0,
+            values: values.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub fn matches(self, pattern: &str) -> Matcher {
+        regex(RegexTarget::Query, Some(self.0), pattern)
+    }
+}
+
+pub struct BodyBuilder;
+
+pub fn body() -> BodyBuilder {
+    BodyBuilder
+}
+
+impl BodyBuilder {
+    pub fn contains(self, value: impl Into<String>) -> Matcher {
+        Matcher::BodyContains(BodyContainsMatcher {
+            values: vec![value.into()],
+        })
+    }
+
+    pub fn matches(self, pattern: &str) -> Matcher {
+        regex(RegexTarget::Body, None, pattern)
+    }
+}
+
+pub struct JsonPathBuilder(BodyJsonPathMatcher);
+
+pub fn json_path(path: &str) -> JsonPathBuilder {
+    let path = JsonPath::parse(path).unwrap_or_else(|e| panic!("Invalid JSONPath {path} - {e}"));
+    JsonPathBuilder(BodyJsonPathMatcher {
+        path,
+        exists: None,
+        equals: None,
+        one_of: None,
+        matches: None,
+    })
+}
+
+impl JsonPathBuilder {
+    pub fn exists(self) -> Matcher {
+        Matcher::BodyJsonPath(self.0)
+    }
+
+    pub fn missing(mut self) -> Matcher {
+        self.0.exists = Some(false);
+        Matcher::BodyJsonPath(self.0)
+    }
+
+    pub fn equals(mut self, value: impl Into<Value>) -> Matcher {
+        self.0.equals = Some(value.into());
+        Matcher::BodyJsonPath(self.0)
+    }
+
+    pub fn one_of<V: Into<Value>>(mut self, values: impl IntoIterator<Item = V>) -> Matcher {
+        self.0.one_of = Some(values.into_iter().map(Into::into).collect());
+        Matcher::BodyJsonPath(self.0)
+    }
+
+    pub fn matches(mut self, value: &str) -> Matcher {
+        self.0.matches = Some(pattern(value));
+        Matcher::BodyJsonPath(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_test::TestServer;
+    use serde_json::json;
+
+    use crate::routing::builder::{body, header, json_path, path_param, query};
+    use crate::routing::config::Config;
+    use crate::routing::options::Options;
+    use crate::routing::route::Route;
+    use crate::state::SharedState;
+
+    fn login() -> Route {
+        Route::post("/v1/login/{tenant}")
+            .when(header("Authorization").contains("Basic"))
+            .when(path_param("tenant").equals("acme"))
+            .respond(200)
+            .json(json!({"token": "abc"}))
+            .when_any(vec![
+                json_path("$.user").missing(),
+                query("debug").contains("true"),
+                body().matches("^$"),
+            ])
+            .respond(400)
+            .respond(401)
+    }
+
+    #[test]
+    fn test_builder_creates_same_route_as_yaml() {
+        let yaml = serde_yaml::to_string(&login()).unwrap();
+        let expected: Route = serde_yaml::from_str(
+            r#"
+            path: /v1/login/{tenant}
+            methods:
+              - POST
+            conditions:
+              - and:
+                  - type: HeaderContains
+                    with:
+                      name: Authorization
+                      values:
+                        - Basic
+                  - type: PathParam
+                    with:
+                      name: tenant
+                      values:
+                        - acme
+                response:
+                  status: 200
+                  headers:
+                    Content-Type: application/json
+                  body: '{"token":"abc"}'
+              - or:
+                  - type: BodyJsonPath
+                    with:
+                      path: $.user
+                      exists: false
+                  - type: QueryContains
+                    with:
+                      name: debug
+                      values:
+                        - 'true'
+                  - type: Regex
+                    with:
+                      target: body
+                      pattern: ^$
+                response:
+                  status: 400
+              - response:
+                  status: 401
+            "#,
+        )
+        .unwrap();
+        assert_eq!(yaml, serde_yaml::to_string(&expected).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_built_config_is_served() {
+        let config = Config::new(Options::default()).route(login());
+        let server = TestServer::new(config.router(&SharedState::default()).unwrap()).unwrap();
+        let resp = server
+            .post("/v1/login/acme")
+            .add_header("Authorization", "Basic dXNlcg==")
+            .text(r#"{"user": "mocked"}"#)
+            .await;
+        resp.assert_status_ok();
+        resp.assert_json(&json!({"token": "abc"}));
+        let resp = server.post("/v1/login/acme").text("").await;
+        resp.assert_status_bad_request();
+        let resp = server
+            .post("/v1/login/other")
+            .text(r#"{"user": "mocked"}"#)
+            .await;
+        resp.assert_status_unauthorized();
+    }
+}
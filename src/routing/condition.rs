@@ -1,26 +1,55 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::Rng;
+use serde::de::{DeserializeOwned, Error, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::request::Request;
 use crate::routing::matcher::Matcher;
 use crate::routing::matchers::Matchers;
 use crate::routing::response::Response;
+use crate::scenarios::Scenarios;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Condition {
-    #[serde(flatten)]
+    #[serde(flatten, deserialize_with = "deserialize_matcher")]
     pub matcher: Option<Matcher>,
-    #[serde(flatten)]
+    #[serde(flatten, deserialize_with = "deserialize_matchers")]
     pub matchers: Option<Matchers>,
-    pub response: Response,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Response>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub responses: Vec<Response>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Mode>,
+}
+
+/// How `responses` are picked on consecutive calls. Defaults to `Random` if
+/// any response has a weight, otherwise to `Sequence`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Advance on each call and stick on the last response.
+    Sequence,
+    /// Advance on each call and start over after the last response.
+    Cycle,
+    /// Pick a random response on each call, weighted by `weight` (default 1).
+    Random,
 }
 
 impl Condition {
-    pub fn matches(&self, req: &Request) -> bool {
+    pub(crate) fn matches(&self, req: &Request) -> bool {
         match self.matcher.clone() {
-            Some(single_matcher) => single_matcher.matches(&req),
+            Some(single_matcher) => single_matcher.matches(req),
             None => match self.matchers.clone() {
-                Some(matchers) => matchers.matches(&req),
+                Some(matchers) => matchers.matches(req),
                 None => {
                     // both are unset => request matches in any case
                     true
@@ -28,6 +57,98 @@ impl Condition {
             },
         }
     }
+
+    /// All responses of this condition, e.g. to find included files.
+    pub fn all_responses(&self) -> impl Iterator<Item = &Response> {
+        self.response.iter().chain(self.responses.iter())
+    }
+
+    /// Picks the response for the given call, counting from zero. `responses`
+    /// take precedence over `response`.
+    pub fn select_response(&self, call: usize, rng: &mut impl Rng) -> Option<&Response> {
+        let count = self.responses.len();
+        if count == 0 {
+            return self.response.as_ref();
+        }
+        let index = match self.mode() {
+            Mode::Sequence => call.min(count - 1),
+            Mode::Cycle => call % count,
+            Mode::Random => {
+                let weights = self.responses.iter().map(|r| r.weight.unwrap_or(1));
+                // fails if all weights are zero
+                WeightedIndex::new(weights).ok()?.sample(rng)
+            }
+        };
+        self.responses.get(index)
+    }
+
+    fn mode(&self) -> Mode {
+        self.mode.unwrap_or_else(|| {
+            if self.responses.iter().any(|r| r.weight.is_some()) {
+                Mode::Random
+            } else {
+                Mode::Sequence
+            }
+        })
+    }
+
+    /// Conditions that require a scenario state only apply while the scenario is in that state.
+    pub(crate) fn in_state(&self, scenarios: &Scenarios) -> bool {
+        match (&self.scenario, &self.state) {
+            (Some(scenario), Some(state)) => scenarios.state(scenario) == *state,
+            _ => true,
+        }
+    }
+}
+
+pub(crate) fn deserialize_matcher<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Matcher>, D::Error> {
+    deserialize_flattened(deserializer, &["type", "with"])
+}
+
+pub(crate) fn deserialize_matchers<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Matchers>, D::Error> {
+    deserialize_flattened(deserializer, &["and", "or"])
+}
+
+/// A flattened `Option` swallows every error of its inner type and silently
+/// turns into `None`, which would make a broken matcher a catch-all. Collect
+/// the relevant fields first and deserialize them explicitly instead.
+fn deserialize_flattened<'de, D, T>(
+    deserializer: D,
+    fields: &'static [&'static str],
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    struct FieldsVisitor;
+
+    impl<'de> Visitor<'de> for FieldsVisitor {
+        type Value = serde_json::Map<String, serde_json::Value>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a condition")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut fields = serde_json::Map::new();
+            while let Some((key, value)) = map.next_entry()? {
+                fields.insert(key, value);
+            }
+            Ok(fields)
+        }
+    }
+
+    let fields = deserializer.deserialize_struct("Condition", fields, FieldsVisitor)?;
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    T::deserialize(serde_json::Value::Object(fields))
+        .map(Some)
+        .map_err(D::Error::custom)
 }
 
 #[cfg(test)]
@@ -35,6 +156,8 @@ mod tests {
     use std::collections::HashMap;
 
     use axum::http::HeaderMap;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     use crate::request::Request;
     use crate::routing::body::Body;
@@ -55,11 +178,13 @@ mod tests {
                 name: String::from("foo"),
                 values: vec![Value::Integer(234)],
             })])),
-            response: Response {
+            response: Some(Response {
                 status: 200,
                 headers: HashMap::new(),
                 body: Some(Body::empty()),
-            },
+                ..Default::default()
+            }),
+            ..Default::default()
         };
         let mut path_params = HashMap::new();
         path_params.insert(String::from("foo"), String::from("234"));
@@ -68,6 +193,7 @@ mod tests {
             headers: HeaderMap::new(),
             query: HashMap::new(),
             body: String::new(),
+            client_cert: None,
         };
         let matches = condition.matches(&req);
         assert!(!matches);
@@ -85,11 +211,13 @@ mod tests {
                 name: String::from("foo"),
                 values: vec![Value::Integer(234)],
             })])),
-            response: Response {
+            response: Some(Response {
                 status: 200,
                 headers: HashMap::new(),
                 body: Some(Body::empty()),
-            },
+                ..Default::default()
+            }),
+            ..Default::default()
         };
         let mut path_params = HashMap::new();
         path_params.insert(String::from("foo"), String::from("234"));
@@ -98,6 +226,7 @@ mod tests {
             headers: HeaderMap::new(),
             query: HashMap::new(),
             body: String::new(),
+            client_cert: None,
         };
         let matches = condition.matches(&req);
         assert!(matches);
@@ -108,11 +237,13 @@ mod tests {
         let condition = Condition {
             matcher: None,
             matchers: None,
-            response: Response {
+            response: Some(Response {
                 status: 200,
                 headers: HashMap::new(),
                 body: Some(Body::empty()),
-            },
+                ..Default::default()
+            }),
+            ..Default::default()
         };
         let mut path_params = HashMap::new();
         path_params.insert(String::from("foo"), String::from("234"));
@@ -121,8 +252,142 @@ mod tests {
             headers: HeaderMap::new(),
             query: HashMap::new(),
             body: String::new(),
+            client_cert: None,
         };
         let matches = condition.matches(&req);
         assert!(matches);
     }
+
+    #[test]
+    fn test_invalid_matcher_is_not_a_catch_all() {
+        let condition: Result<Condition, _> = serde_yaml::from_str(
+            r#"
+            type: Regex
+            with:
+              target: header
+              name: Authorization
+              pattern: ^(Bearer
+            response:
+              status: 200
+            "#,
+        );
+        assert!(condition.is_err());
+    }
+
+    #[test]
+    fn test_combined_matchers_are_deserialized() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            or:
+              - type: PathParam
+                with:
+                  name: foo
+                  values:
+                    - 234
+            response:
+              status: 200
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(condition.matchers, Some(Matchers::Or(_))));
+        let serialized = serde_yaml::to_string(&condition).unwrap();
+        let condition: Condition = serde_yaml::from_str(&serialized).unwrap();
+        assert!(matches!(condition.matchers, Some(Matchers::Or(_))));
+    }
+
+    #[test]
+    fn test_condition_without_matcher_is_deserialized() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            response:
+              status: 200
+            "#,
+        )
+        .unwrap();
+        assert!(condition.matcher.is_none());
+        assert!(condition.matchers.is_none());
+    }
+
+    fn statuses(condition: &Condition, calls: usize) -> Vec<u16> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..calls)
+            .map(|call| condition.select_response(call, &mut rng).unwrap().status)
+            .collect()
+    }
+
+    #[test]
+    fn test_responses_are_returned_in_sequence() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            responses:
+              - status: 503
+              - status: 503
+              - status: 200
+            "#,
+        )
+        .unwrap();
+        assert_eq!(statuses(&condition, 5), vec![503, 503, 200, 200, 200]);
+    }
+
+    #[test]
+    fn test_responses_are_cycled() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            mode: cycle
+            responses:
+              - status: 200
+              - status: 500
+            "#,
+        )
+        .unwrap();
+        assert_eq!(statuses(&condition, 4), vec![200, 500, 200, 500]);
+    }
+
+    #[test]
+    fn test_random_responses_are_picked_from_list() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            mode: random
+            responses:
+              - status: 200
+              - status: 500
+            "#,
+        )
+        .unwrap();
+        assert!(statuses(&condition, 20)
+            .iter()
+            .all(|status| [200, 500].contains(status)));
+    }
+
+    #[test]
+    fn test_weighted_responses_are_random_and_reproducible() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            responses:
+              - status: 200
+                weight: 9
+              - status: 500
+                weight: 1
+            "#,
+        )
+        .unwrap();
+        let first = statuses(&condition, 1000);
+        assert_eq!(first, statuses(&condition, 1000));
+        let errors = first.iter().filter(|s| **s == 500).count();
+        assert!((50..150).contains(&errors), "{errors} errors");
+    }
+
+    #[test]
+    fn test_zero_weights_select_nothing() {
+        let condition: Condition = serde_yaml::from_str(
+            r#"
+            responses:
+              - status: 200
+                weight: 0
+            "#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(condition.select_response(0, &mut rng).is_none());
+    }
 }
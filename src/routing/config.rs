@@ -1,19 +1,130 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::{fmt, fs, io};
 
-use axum::Router;
+use axum::extract::{MatchedPath, Request};
+use axum::http::header::ALLOW;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Router};
 use serde::{Deserialize, Serialize};
 
+use crate::mocks::{Mocks, RouteError};
+use crate::proxy;
+use crate::routing::body::Body;
+use crate::routing::format::{Format, FormatError};
 use crate::routing::options::Options;
 use crate::routing::route::Route;
+use crate::routing::server::Server;
+use crate::state::SharedState;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(FormatError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Unable to read config - {e}"),
+            ConfigError::Parse(e) => write!(f, "Failed to parse config - {e}"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
     pub options: Options,
+    #[serde(default)]
     pub routes: Vec<Route>,
+    /// Further servers on their own ports or for their own hosts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<Server>,
 }
 
 impl Config {
-    pub fn router(&self) -> Router {
+    /// Reads the config in the format of the file extension, see [`Format`].
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let config = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Format::from_path(path)
+            .parse(&config)
+            .map_err(ConfigError::Parse)
+    }
+
+    /// Writes the config in the format of the file extension.
+    pub fn to_file(&self, path: &Path) -> io::Result<()> {
+        let config = Format::from_path(path)
+            .write(self)
+            .map_err(io::Error::other)?;
+        fs::write(path, config)
+    }
+
+    /// All files that are included as response bodies.
+    pub fn includes(&self) -> Vec<PathBuf> {
+        self.routes
+            .iter()
+            .chain(self.servers.iter().flat_map(|s| s.routes.iter()))
+            .flat_map(|r| r.conditions.iter())
+            .flat_map(|c| c.all_responses())
+            .filter_map(|r| match &r.body {
+                Some(Body::Include(include)) => Some(include.include.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Splits the config into one config per address and port, starting with
+    /// the one of the options. Servers on the same port are told apart by the
+    /// hosts of their routes, they cannot set another `tls` than their port.
+    pub fn listeners(&self) -> Result<Vec<Config>, RouteError> {
+        let mut listeners = vec![Config {
+            servers: Vec::new(),
+            ..self.clone()
+        }];
+        for (index, server) in self.servers.iter().enumerate() {
+            let address = server.address.as_ref().unwrap_or(&self.options.address);
+            let port = server.port.unwrap_or(self.options.port);
+            let routes = server.routes.iter().cloned().map(|mut route| {
+                if route.hosts.is_empty() {
+                    route.hosts = server.hosts.clone();
+                }
+                route
+            });
+            match listeners
+                .iter_mut()
+                .find(|l| l.options.address == *address && l.options.port == port)
+            {
+                Some(listener) => {
+                    if server.tls.is_some() && server.tls != listener.options.tls {
+                        return Err(RouteError::SharedTls {
+                            server: index,
+                            address: address.clone(),
+                            port,
+                        });
+                    }
+                    listener.routes.extend(routes);
+                }
+                None => listeners.push(Config {
+                    options: Options {
+                        address: address.clone(),
+                        port,
+                        tls: server.tls.clone(),
+                        ..self.options.clone()
+                    },
+                    routes: routes.collect(),
+                    servers: Vec::new(),
+                }),
+            }
+        }
+        Ok(listeners)
+    }
+
+    /// The shared state outlives the router, e.g. when the config is reloaded.
+    /// Configs that cannot be served are rejected, see [`Mocks::validate`].
+    pub(crate) fn router(&self, shared: &SharedState) -> Result<Router, RouteError> {
+        Mocks::validate(self)?;
         let router =
             self.inherit_enable_cors()
                 .routes
@@ -23,28 +134,58 @@ impl Config {
                     let route = Arc::new(next);
                     acc.merge(route.router())
                 });
-        router
+        // the first route of a path with a proxy decides where to forward to
+        let upstreams: Arc<HashMap<String, String>> = Arc::new(
+            self.routes
+                .iter()
+                .rev()
+                .filter_map(|r| Some((r.path.clone(), r.proxy.clone()?)))
+                .collect(),
+        );
+        // methods a path has no route for are forwarded like unmatched requests
+        let router = router.method_not_allowed_fallback(
+            move |Extension(options): Extension<Options>, path: MatchedPath, req: Request| {
+                let upstream = upstreams
+                    .get(path.as_str())
+                    .or(options.proxy.as_ref())
+                    .cloned();
+                async move {
+                    let Some(upstream) = upstream else {
+                        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+                    };
+                    let mut resp = proxy::forward_request(upstream, req).await;
+                    if !resp.headers().contains_key(ALLOW) {
+                        resp.extensions_mut().insert(proxy::WithoutAllow);
+                    }
+                    resp
+                }
+            },
+        );
+        Ok(router
+            .layer(Extension(self.options.clone()))
+            .layer(Extension(shared.clone())))
     }
 
     fn inherit_enable_cors(&self) -> Self {
-        let options = self.options.clone();
         let routes = self
             .routes
             .iter()
             .cloned()
-            .map(|mut r| {
-                // only update it if it is not set in it's own config
-                if r.enable_cors.is_none() {
-                    r.enable_cors = options.enable_cors;
-                }
-                r.clone()
-            })
+            .map(|r| self.inherit_enable_cors_for(r))
             .collect();
         Self {
-            options: self.options.clone(),
             routes,
+            ..self.clone()
         }
     }
+
+    pub fn inherit_enable_cors_for(&self, mut route: Route) -> Route {
+        // only update it if it is not set in it's own config
+        if route.enable_cors.is_none() {
+            route.enable_cors = self.options.enable_cors;
+        }
+        route
+    }
 }
 
 #[cfg(test)]
@@ -52,7 +193,10 @@ mod tests {
     use axum_test::http::{HeaderName, HeaderValue};
     use axum_test::TestServer;
 
+    use crate::mocks::RouteError;
     use crate::routing::config::Config;
+    use crate::routing::server::Server;
+    use crate::state::SharedState;
 
     #[tokio::test]
     async fn test_create_router() {
@@ -81,7 +225,7 @@ mod tests {
                             string"#;
         let config: Config = serde_yaml::from_str(config_str).unwrap();
         println!("{:?}", config);
-        let router = config.router();
+        let router = config.router(&SharedState::default()).unwrap();
         let server = TestServer::new(router).unwrap();
         let auth_header = HeaderName::from_lowercase(b"authorization").unwrap();
         let header_value = HeaderValue::from_str("Basic foofoo").unwrap();
@@ -93,4 +237,146 @@ mod tests {
         resp.assert_status_ok();
         resp.assert_text("this is a string");
     }
+
+    #[test]
+    fn test_includes() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+                options:
+                  address: localhost
+                  port: 3003
+                routes:
+                  - path: /test
+                    methods:
+                      - GET
+                    conditions:
+                      - type: HeaderContains
+                        with:
+                          name: Authorization
+                          values:
+                            - Basic
+                        response:
+                          status: 200
+                          body:
+                            include: a.json
+                      - response:
+                          status: 200
+                          body: b.json"#,
+        )
+        .unwrap();
+        assert_eq!(config.includes(), vec![std::path::PathBuf::from("a.json")]);
+    }
+
+    #[tokio::test]
+    async fn test_responses_advance_per_call() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+                options:
+                  address: localhost
+                  port: 3003
+                routes:
+                  - path: /flaky
+                    methods:
+                      - GET
+                    conditions:
+                      - responses:
+                          - status: 503
+                          - status: 200
+                            body: ok"#,
+        )
+        .unwrap();
+        let server = TestServer::new(config.router(&SharedState::default()).unwrap()).unwrap();
+        server
+            .get("/flaky")
+            .await
+            .assert_status_service_unavailable();
+        server.get("/flaky").await.assert_text("ok");
+        server.get("/flaky").await.assert_text("ok");
+    }
+
+    #[test]
+    fn test_servers_are_split_into_listeners() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+                options:
+                  address: localhost
+                  port: 8000
+                routes: []
+                servers:
+                  - port: 8001
+                    routes:
+                      - path: /payments
+                        methods:
+                          - GET
+                        conditions: []
+                  - hosts:
+                      - users.local
+                    routes:
+                      - path: /users
+                        methods:
+                          - GET
+                        conditions: []
+                      - path: /health
+                        methods:
+                          - GET
+                        hosts:
+                          - health.local
+                        conditions: []"#,
+        )
+        .unwrap();
+        let listeners = config.listeners().unwrap();
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(listeners[0].options.port, 8000);
+        assert_eq!(listeners[0].routes[0].hosts, vec!["users.local"]);
+        assert_eq!(listeners[0].routes[1].hosts, vec!["health.local"]);
+        assert_eq!(listeners[1].options.port, 8001);
+        assert_eq!(listeners[1].options.address, "localhost");
+        assert_eq!(listeners[1].routes[0].path, "/payments");
+    }
+
+    #[test]
+    fn test_servers_sharing_a_port_keep_its_tls() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+                options:
+                  address: localhost
+                  port: 8000
+                routes: []
+                servers:
+                  - port: 8001
+                    tls:
+                      self_signed: true
+                    routes: []
+                  - port: 8001
+                    hosts:
+                      - users.local
+                    routes: []
+                  - port: 8001
+                    hosts:
+                      - admin.local
+                    tls:
+                      self_signed: true
+                    routes: []"#,
+        )
+        .unwrap();
+        let listeners = config.listeners().unwrap();
+        assert!(listeners[0].options.tls.is_none());
+        assert!(listeners[1].options.tls.is_some());
+
+        let shared = Config {
+            servers: vec![Server {
+                tls: config.servers[0].tls.clone(),
+                ..Server::default()
+            }],
+            ..config
+        };
+        assert!(matches!(
+            shared.listeners(),
+            Err(RouteError::SharedTls {
+                server: 0,
+                port: 8000,
+                ..
+            })
+        ));
+    }
 }
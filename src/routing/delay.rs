@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal};
+use serde::{Deserialize, Serialize};
+
+/// Samples are capped, e.g. a lognormal with a large sigma can draw delays
+/// too long to be represented.
+pub const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// The distribution response delays are drawn from, all values in milliseconds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Delay {
+    Fixed { ms: u64 },
+    Uniform { min: u64, max: u64 },
+    Normal { mean: f64, stddev: f64 },
+    Lognormal { median: f64, sigma: f64 },
+}
+
+impl Delay {
+    /// Draws a delay. Negative samples are treated as no delay, samples above
+    /// [`MAX_DELAY`] as `MAX_DELAY` and invalid parameters fall back to the
+    /// mean or median.
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let ms = match *self {
+            Delay::Fixed { ms } => ms as f64,
+            Delay::Uniform { min, max } => rng.random_range(min..=max.max(min)) as f64,
+            Delay::Normal { mean, stddev } => Normal::new(mean, stddev)
+                .map(|d| d.sample(rng))
+                .unwrap_or(mean),
+            Delay::Lognormal { median, sigma } => LogNormal::new(median.ln(), sigma)
+                .map(|d| d.sample(rng))
+                .unwrap_or(median),
+        };
+        Duration::try_from_secs_f64(ms.max(0.0) / 1000.0)
+            .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::routing::delay::{Delay, MAX_DELAY};
+
+    #[test]
+    fn test_delays_are_deserialized() {
+        let delays: Vec<Delay> = serde_yaml::from_str(
+            r#"
+            - type: fixed
+              ms: 100
+            - type: uniform
+              min: 10
+              max: 20
+            - type: normal
+              mean: 200
+              stddev: 50
+            - type: lognormal
+              median: 80
+              sigma: 0.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(delays[0], Delay::Fixed { ms: 100 });
+        assert_eq!(delays[1], Delay::Uniform { min: 10, max: 20 });
+        assert!(matches!(delays[2], Delay::Normal { .. }));
+        assert!(matches!(delays[3], Delay::Lognormal { .. }));
+    }
+
+    #[test]
+    fn test_samples_follow_distribution() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(
+            Delay::Fixed { ms: 100 }.sample(&mut rng),
+            Duration::from_millis(100)
+        );
+        let uniform = Delay::Uniform { min: 10, max: 20 };
+        assert!((0..100)
+            .map(|_| uniform.sample(&mut rng))
+            .all(|d| d >= Duration::from_millis(10) && d <= Duration::from_millis(20)));
+        let normal = Delay::Normal {
+            mean: 200.0,
+            stddev: 10.0,
+        };
+        let mean = (0..1000)
+            .map(|_| normal.sample(&mut rng).as_secs_f64() * 1000.0)
+            .sum::<f64>()
+            / 1000.0;
+        assert!((190.0..210.0).contains(&mean), "mean {mean}");
+        let lognormal = Delay::Lognormal {
+            median: 80.0,
+            sigma: 0.5,
+        };
+        let mut samples: Vec<Duration> = (0..1001).map(|_| lognormal.sample(&mut rng)).collect();
+        samples.sort();
+        let median = samples[500].as_secs_f64() * 1000.0;
+        assert!((70.0..90.0).contains(&median), "median {median}");
+    }
+
+    #[test]
+    fn test_negative_samples_are_no_delay() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let normal = Delay::Normal {
+            mean: -100.0,
+            stddev: 1.0,
+        };
+        assert_eq!(normal.sample(&mut rng), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_huge_samples_are_capped() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays = [
+            Delay::Fixed { ms: u64::MAX },
+            Delay::Uniform {
+                min: u64::MAX - 1,
+                max: u64::MAX,
+            },
+            Delay::Normal {
+                mean: f64::MAX,
+                stddev: 1.0,
+            },
+            Delay::Lognormal {
+                median: 80.0,
+                sigma: 1000.0,
+            },
+            Delay::Lognormal {
+                median: f64::INFINITY,
+                sigma: 0.5,
+            },
+        ];
+        for delay in delays {
+            assert!(delay.sample(&mut rng) <= MAX_DELAY, "{delay:?}");
+        }
+    }
+}
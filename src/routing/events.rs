@@ -0,0 +1,164 @@
+use std::convert::Infallible;
+use std::future;
+use std::time::Duration;
+
+use futures_util::stream;
+use serde::{Deserialize, Serialize};
+
+/// A `text/event-stream` body. The connection is kept open after the last
+/// event until an event with `close` is sent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Events {
+    pub events: Vec<Event>,
+    /// Start over after the last event.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub repeat: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Event {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<u64>,
+    /// The time to wait before this event is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    /// Close the connection after this event.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub close: bool,
+}
+
+impl Event {
+    fn format(&self) -> String {
+        let mut event = String::new();
+        if let Some(name) = &self.event {
+            event.push_str(&format!("event: {name}\n"));
+        }
+        if let Some(id) = &self.id {
+            event.push_str(&format!("id: {id}\n"));
+        }
+        if let Some(retry) = self.retry {
+            event.push_str(&format!("retry: {retry}\n"));
+        }
+        // every line of multi line data needs its own field
+        self.data.iter().flat_map(|d| d.lines()).for_each(|line| {
+            event.push_str(&format!("data: {line}\n"));
+        });
+        event.push('\n');
+        event
+    }
+}
+
+impl Events {
+    /// All events sent once, without delays.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.events
+            .iter()
+            .map(Event::format)
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    pub fn body(&self) -> axum::body::Body {
+        let Events { events, repeat } = self.clone();
+        let stream = stream::unfold((0, false), move |(index, closed)| {
+            let events = events.clone();
+            async move {
+                if closed {
+                    return None;
+                }
+                let event = match events.get(index) {
+                    Some(event) => event,
+                    None if repeat && !events.is_empty() => &events[0],
+                    None => future::pending().await,
+                };
+                if let Some(delay) = event.delay_ms {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                }
+                let next = (index % events.len() + 1, event.close);
+                Some((Ok::<_, Infallible>(event.format()), next))
+            }
+        });
+        axum::body::Body::from_stream(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+
+    use crate::routing::events::{Event, Events};
+
+    fn event(data: &str) -> Event {
+        Event {
+            data: Some(data.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_event_is_formatted() {
+        let event = Event {
+            event: Some(String::from("update")),
+            id: Some(String::from("7")),
+            retry: Some(1000),
+            ..event("first\nsecond")
+        };
+        assert_eq!(
+            event.format(),
+            "event: update\nid: 7\nretry: 1000\ndata: first\ndata: second\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_with_close() {
+        let events = Events {
+            events: vec![
+                event("a"),
+                Event {
+                    close: true,
+                    delay_ms: Some(10),
+                    ..event("b")
+                },
+            ],
+            repeat: false,
+        };
+        let body = axum::body::to_bytes(events.body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"data: a\n\ndata: b\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_kept_open_or_repeated() {
+        let events = Events {
+            events: vec![event("a"), event("b")],
+            repeat: false,
+        };
+        let mut stream = events.body().into_data_stream();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "data: a\n\n");
+        assert_eq!(stream.next().await.unwrap().unwrap(), "data: b\n\n");
+        let next = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(next.is_err());
+
+        let events = Events {
+            repeat: true,
+            ..events
+        };
+        let data: Vec<_> = events
+            .body()
+            .into_data_stream()
+            .take(5)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(data[4], "data: a\n\n");
+    }
+}
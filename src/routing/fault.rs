@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// A broken response. Faults are applied by the server to the underlying
+/// connection instead of sending the configured response.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    /// Reset the TCP connection without replying.
+    ConnectionReset,
+    /// Close the connection without replying.
+    EmptyReply,
+    /// Advertise the full Content-Length but only send half of the body.
+    TruncatedBody,
+    /// Send the body using chunked encoding with a wrong chunk size.
+    MalformedChunked,
+    /// Never respond.
+    Hang,
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The formats a config can be written in, picked by the file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Yaml(serde_yaml::Error),
+    Json(serde_json::Error),
+    TomlParse(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Yaml(e) => write!(f, "{e}"),
+            FormatError::Json(e) => write!(f, "{e}"),
+            FormatError::TomlParse(e) => write!(f, "{}", e.message()),
+            FormatError::TomlWrite(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl FormatError {
+    /// The line and column of a parse error in `source`, both starting at 1.
+    pub fn location(&self, source: &str) -> Option<(usize, usize)> {
+        match self {
+            FormatError::Yaml(e) => e.location().map(|l| (l.line(), l.column())),
+            FormatError::Json(e) if e.line() > 0 => Some((e.line(), e.column())),
+            FormatError::TomlParse(e) => e.span().map(|span| {
+                let before = &source[..span.start.min(source.len())];
+                let line = before.matches('\n').count() + 1;
+                let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                (line, column)
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Format {
+    /// Files without a `.json` or `.toml` extension are YAML.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Format::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Yaml,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, source: &str) -> Result<T, FormatError> {
+        match self {
+            Format::Yaml => serde_yaml::from_str(source).map_err(FormatError::Yaml),
+            Format::Json => serde_json::from_str(source).map_err(FormatError::Json),
+            Format::Toml => toml::from_str(source).map_err(FormatError::TomlParse),
+        }
+    }
+
+    pub fn write<T: Serialize>(self, value: &T) -> Result<String, FormatError> {
+        match self {
+            Format::Yaml => serde_yaml::to_string(value).map_err(FormatError::Yaml),
+            Format::Json => serde_json::to_string_pretty(value)
+                .map(|json| json + "\n")
+                .map_err(FormatError::Json),
+            Format::Toml => toml::to_string_pretty(value).map_err(FormatError::TomlWrite),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::routing::config::Config;
+    use crate::routing::format::Format;
+
+    const CONFIG: &str = r#"
+options:
+  address: localhost
+  port: 3003
+  enable_cors: true
+routes:
+  - path: /v1/users/{id}
+    methods:
+      - GET
+      - DELETE
+    delay:
+      type: fixed
+      ms: 10
+    conditions:
+      - and:
+          - type: PathParam
+            with:
+              name: id
+              values:
+                - 42
+                - mocked
+          - type: BodyJsonPath
+            with:
+              path: $.user
+              exists: false
+        response:
+          status: 200
+          headers:
+            Content-Type: application/json
+          body: '{"name": "mocked"}'
+      - scenario: checkout
+        state: paid
+        responses:
+          - status: 404
+            weight: 3
+          - status: 503
+            fault: truncated_body
+"#;
+
+    #[test]
+    fn test_format_is_detected_from_extension() {
+        assert_eq!(Format::from_path(Path::new("mocked.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("mocked.TOML")), Format::Toml);
+        assert_eq!(Format::from_path(Path::new("mocked.yaml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("mocked")), Format::Yaml);
+    }
+
+    #[test]
+    fn test_config_survives_all_formats() {
+        let config: Config = Format::Yaml.parse(CONFIG).unwrap();
+        let expected = Format::Yaml.write(&config).unwrap();
+        for format in [Format::Json, Format::Toml] {
+            let written = format.write(&config).unwrap();
+            let parsed: Config = format.parse(&written).unwrap();
+            assert_eq!(Format::Yaml.write(&parsed).unwrap(), expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_toml_error_has_location() {
+        let source = "[options]\naddress = \"localhost\"\nport = \"high\"\n";
+        let error = Format::Toml.parse::<Config>(source).unwrap_err();
+        assert_eq!(error.location(source), Some((3, 8)));
+    }
+}
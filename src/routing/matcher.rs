@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
+use serde_json_path::JsonPath;
 
 use crate::request::Request;
+use crate::routing::pattern::Pattern;
 use crate::routing::Matching;
 use crate::routing::value::Value;
 
@@ -10,16 +12,22 @@ pub enum Matcher {
     PathParam(PathParamMatcher),
     HeaderContains(HeaderContainsMatcher),
     QueryContains(QueryContainsMatcher),
-    BodyContains(BodyContainsMatcher)
+    BodyContains(BodyContainsMatcher),
+    Regex(RegexMatcher),
+    BodyJsonPath(BodyJsonPathMatcher),
+    ClientCert(ClientCertMatcher),
 }
 
 impl Matcher {
-    pub fn matches(&self, req: &Request) -> bool {
+    pub(crate) fn matches(&self, req: &Request) -> bool {
         match self {
             Matcher::PathParam(matcher) => matcher.matches(req),
             Matcher::HeaderContains(matcher) => matcher.matches(req),
             Matcher::QueryContains(matcher) => matcher.matches(req),
             Matcher::BodyContains(matcher) => matcher.matches(req),
+            Matcher::Regex(matcher) => matcher.matches(req),
+            Matcher::BodyJsonPath(matcher) => matcher.matches(req),
+            Matcher::ClientCert(matcher) => matcher.matches(req),
         }
     }
 }
@@ -97,6 +105,157 @@ impl Matching for BodyContainsMatcher {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegexTarget {
+    Header,
+    Query,
+    Path,
+    Body,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RegexMatcher {
+    pub target: RegexTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub pattern: Pattern,
+}
+
+impl RegexMatcher {
+    fn value<'a>(&self, req: &'a Request) -> Option<&'a str> {
+        if self.target == RegexTarget::Body {
+            return Some(req.body.as_str());
+        }
+        let name = self.name.as_ref()?;
+        match self.target {
+            RegexTarget::Header => req.headers.get(name).and_then(|v| v.to_str().ok()),
+            RegexTarget::Query => req.query.get(name).map(String::as_str),
+            RegexTarget::Path => req.path_params.get(name).map(String::as_str),
+            RegexTarget::Body => unreachable!(),
+        }
+    }
+}
+
+impl Matching for RegexMatcher {
+    fn matches(&self, req: &Request) -> bool {
+        if let Some(value) = self.value(req) {
+            let matches = self.pattern.is_match(value);
+            if matches {
+                log::info!(
+                    "RegexMatcher matches {:?} for {:?} {}",
+                    self.pattern,
+                    self.target,
+                    self.name.as_deref().unwrap_or("")
+                );
+            }
+            return matches;
+        }
+        false
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BodyJsonPathMatcher {
+    pub path: JsonPath,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "crate::routing::value::some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub equals: Option<Value>,
+    #[serde(default, rename = "in", skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<Pattern>,
+}
+
+impl BodyJsonPathMatcher {
+    fn node_matches(&self, node: &serde_json::Value) -> bool {
+        let value = Value::from_json(node);
+        if let Some(expected) = &self.equals {
+            if value.as_ref() != Some(expected) {
+                return false;
+            }
+        }
+        if let Some(values) = &self.one_of {
+            if !value.as_ref().is_some_and(|v| values.contains(v)) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.matches {
+            let text = match node {
+                serde_json::Value::String(v) => v.clone(),
+                other => other.to_string(),
+            };
+            if !pattern.is_match(&text) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Matching for BodyJsonPathMatcher {
+    fn matches(&self, req: &Request) -> bool {
+        let Ok(body) = serde_json::from_str::<serde_json::Value>(&req.body) else {
+            return false;
+        };
+        let nodes = self.path.query(&body).all();
+        if !self.exists.unwrap_or(true) {
+            return nodes.is_empty();
+        }
+        let matches = nodes.into_iter().any(|node| self.node_matches(node));
+        if matches {
+            log::info!("BodyJsonPathMatcher matches {}", self.path);
+        }
+        matches
+    }
+}
+
+/// Matches the certificate a client presented over mutual TLS. Every field
+/// that is given must contain a matching value, no fields match any
+/// certificate.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientCertMatcher {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub common_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sans: Vec<String>,
+    /// Hex encoded SHA-256 fingerprints, colons and case are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fingerprints: Vec<String>,
+}
+
+impl Matching for ClientCertMatcher {
+    fn matches(&self, req: &Request) -> bool {
+        let Some(cert) = &req.client_cert else {
+            return false;
+        };
+        let common_name = self.common_names.is_empty()
+            || cert
+                .common_name
+                .as_ref()
+                .is_some_and(|cn| self.common_names.contains(cn));
+        let san = self.sans.is_empty() || self.sans.iter().any(|san| cert.sans.contains(san));
+        let fingerprint = self.fingerprints.is_empty()
+            || self
+                .fingerprints
+                .iter()
+                .any(|f| f.replace(':', "").eq_ignore_ascii_case(&cert.fingerprint));
+        let matches = common_name && san && fingerprint;
+        if matches {
+            log::info!(
+                "ClientCertMatcher matches {}",
+                cert.common_name.as_deref().unwrap_or(&cert.fingerprint)
+            );
+        }
+        matches
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -107,11 +266,28 @@ mod tests {
     use crate::routing::matcher::Matcher::PathParam;
     use crate::routing::matcher::Matcher::QueryContains;
     use crate::routing::matcher::PathParamMatcher;
+    use crate::routing::pattern::Pattern;
     use crate::routing::value::Value;
     use crate::routing::Matching;
+    use crate::tls::ClientCert;
 
     use super::BodyContainsMatcher;
     use super::QueryContainsMatcher;
+    use super::{BodyJsonPathMatcher, ClientCertMatcher, Matcher, RegexMatcher, RegexTarget};
+
+    fn json_request(body: &str) -> Request {
+        Request {
+            headers: HeaderMap::new(),
+            query: HashMap::new(),
+            body: body.to_string(),
+            client_cert: None,
+            path_params: HashMap::new(),
+        }
+    }
+
+    fn json_path_matcher(yaml: &str) -> BodyJsonPathMatcher {
+        serde_yaml::from_str(yaml).unwrap()
+    }
 
     #[test]
     fn test_body_contains_matches() {
@@ -120,6 +296,7 @@ mod tests {
         };
         let req = Request {
             body: String::from("This is a foo body"),
+            client_cert: None,
             headers: HeaderMap::new(),
             path_params: HashMap::new(),
             query: HashMap::new(),
@@ -135,6 +312,7 @@ mod tests {
         };
         let req = Request {
             body: String::from("This is a body"),
+            client_cert: None,
             headers: HeaderMap::new(),
             path_params: HashMap::new(),
             query: HashMap::new(),
@@ -155,6 +333,7 @@ mod tests {
             headers: HeaderMap::new(),
             query: HashMap::new(),
             body: "".to_string(),
+            client_cert: None,
             path_params,
         };
         let matches = ppm.matches(&req);
@@ -173,9 +352,217 @@ mod tests {
             headers: HeaderMap::new(),
             query,
             body: "".to_string(),
+            client_cert: None,
             path_params: HashMap::new(),
         };
         let matches = qcm.matches(&req);
         assert!(matches)
     }
+
+    #[test]
+    fn test_regex_matches_header() {
+        let rm = RegexMatcher {
+            target: RegexTarget::Header,
+            name: Some("authorization".to_string()),
+            pattern: Pattern::new("^Bearer [A-Za-z0-9._-]+$").unwrap(),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer abc.def_1".parse().unwrap());
+        let mut req = Request {
+            headers,
+            query: HashMap::new(),
+            body: "".to_string(),
+            client_cert: None,
+            path_params: HashMap::new(),
+        };
+        assert!(rm.matches(&req));
+        req.headers
+            .insert("authorization", "Basic Zm9vOmJhcg==".parse().unwrap());
+        assert!(!rm.matches(&req));
+    }
+
+    #[test]
+    fn test_regex_matches_path_param() {
+        let rm = RegexMatcher {
+            target: RegexTarget::Path,
+            name: Some("id".to_string()),
+            pattern: Pattern::new("^[0-9a-f]{8}-([0-9a-f]{4}-){3}[0-9a-f]{12}$").unwrap(),
+        };
+        let mut path_params = HashMap::new();
+        path_params.insert(
+            "id".to_string(),
+            "3f2b8c4e-1a2b-4c3d-8e9f-0a1b2c3d4e5f".to_string(),
+        );
+        let req = Request {
+            headers: HeaderMap::new(),
+            query: HashMap::new(),
+            body: "".to_string(),
+            client_cert: None,
+            path_params,
+        };
+        assert!(rm.matches(&req));
+    }
+
+    #[test]
+    fn test_regex_matches_body_without_name() {
+        let rm = RegexMatcher {
+            target: RegexTarget::Body,
+            name: None,
+            pattern: Pattern::new(r#""qty":\s*0\b"#).unwrap(),
+        };
+        let req = Request {
+            headers: HeaderMap::new(),
+            query: HashMap::new(),
+            body: r#"{"qty": 0}"#.to_string(),
+            client_cert: None,
+            path_params: HashMap::new(),
+        };
+        assert!(rm.matches(&req));
+    }
+
+    #[test]
+    fn test_regex_is_false_when_value_is_missing() {
+        let rm = RegexMatcher {
+            target: RegexTarget::Query,
+            name: Some("page".to_string()),
+            pattern: Pattern::new(".*").unwrap(),
+        };
+        let req = Request {
+            headers: HeaderMap::new(),
+            query: HashMap::new(),
+            body: "".to_string(),
+            client_cert: None,
+            path_params: HashMap::new(),
+        };
+        assert!(!rm.matches(&req));
+    }
+
+    #[test]
+    fn test_regex_matcher_is_deserialized() {
+        let matcher: Matcher = serde_yaml::from_str(
+            r#"
+            type: Regex
+            with:
+              target: query
+              name: page
+              pattern: ^[0-9]+$
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            matcher,
+            Matcher::Regex(RegexMatcher {
+                target: RegexTarget::Query,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_body_json_path_equals() {
+        let m = json_path_matcher(
+            r#"
+            path: $.order.items[0].qty
+            equals: 0
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"order": {"items": [{"qty": 0}]}}"#)));
+        assert!(!m.matches(&json_request(r#"{"order": {"items": [{"qty": 1}]}}"#)));
+        assert!(!m.matches(&json_request(r#"{"note": "qty: 0"}"#)));
+    }
+
+    #[test]
+    fn test_body_json_path_equals_bool_and_null() {
+        let m = json_path_matcher(
+            r#"
+            path: $.flag
+            equals: true
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"flag": true}"#)));
+        assert!(!m.matches(&json_request(r#"{"flag": false}"#)));
+        let m = json_path_matcher(
+            r#"
+            path: $.flag
+            equals: null
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"flag": null}"#)));
+        assert!(!m.matches(&json_request(r#"{"flag": true}"#)));
+    }
+
+    #[test]
+    fn test_body_json_path_exists() {
+        let m = json_path_matcher("path: $.name");
+        assert!(m.matches(&json_request(r#"{"name": null}"#)));
+        assert!(!m.matches(&json_request(r#"{"other": "name"}"#)));
+        let m = json_path_matcher(
+            r#"
+            path: $.name
+            exists: false
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"other": "name"}"#)));
+    }
+
+    #[test]
+    fn test_body_json_path_in_and_matches() {
+        let m = json_path_matcher(
+            r#"
+            path: $.items[*].sku
+            in:
+              - abc
+              - 42
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"items": [{"sku": "x"}, {"sku": 42}]}"#)));
+        assert!(!m.matches(&json_request(r#"{"items": [{"sku": "x"}]}"#)));
+        let m = json_path_matcher(
+            r#"
+            path: $.email
+            matches: "@example\\.com$"
+            "#,
+        );
+        assert!(m.matches(&json_request(r#"{"email": "foo@example.com"}"#)));
+        assert!(!m.matches(&json_request(r#"{"email": "foo@example.org"}"#)));
+    }
+
+    #[test]
+    fn test_body_json_path_is_false_for_invalid_json() {
+        let m = json_path_matcher("path: $.name");
+        assert!(!m.matches(&json_request("name")));
+    }
+
+    #[test]
+    fn test_invalid_json_path_is_rejected() {
+        let m: Result<BodyJsonPathMatcher, _> = serde_yaml::from_str("path: $.[");
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn test_client_cert_matcher() {
+        let mut req = json_request("");
+        let matcher: ClientCertMatcher = serde_yaml::from_str(
+            r#"
+            common_names:
+              - orders
+            fingerprints:
+              - AB:CD:EF
+            "#,
+        )
+        .unwrap();
+        assert!(!matcher.matches(&req));
+
+        req.client_cert = Some(ClientCert {
+            common_name: Some(String::from("orders")),
+            sans: vec![String::from("spiffe://example.org/orders")],
+            fingerprint: String::from("abcdef"),
+        });
+        assert!(matcher.matches(&req));
+        let matcher = ClientCertMatcher {
+            sans: vec![String::from("spiffe://example.org/billing")],
+            ..matcher
+        };
+        assert!(!matcher.matches(&req));
+    }
 }
@@ -11,7 +11,7 @@ pub enum Matchers {
 }
 
 impl Matchers {
-    pub fn matches(&self, req: &Request) -> bool {
+    pub(crate) fn matches(&self, req: &Request) -> bool {
         match self {
             Matchers::And(matchers) => matchers.iter().all(|matcher| matcher.matches(req)),
             Matchers::Or(matchers) => matchers.iter().any(|matcher| matcher.matches(req)),
@@ -41,6 +41,7 @@ mod tests {
             query: HashMap::new(),
             headers: HeaderMap::new(),
             body: "".to_string(),
+            client_cert: None,
         };
         let matches = m.matches(&req);
         assert!(matches)
@@ -56,6 +57,7 @@ mod tests {
             query: HashMap::new(),
             headers: HeaderMap::new(),
             body: "".to_string(),
+            client_cert: None,
         };
         let matches = m.matches(&req);
         assert!(!matches)
@@ -72,6 +74,7 @@ mod tests {
             query: HashMap::new(),
             headers: HeaderMap::new(),
             body: "".to_string(),
+            client_cert: None,
         };
         let matches = m.matches(&req);
         assert!(matches)
@@ -88,6 +91,7 @@ mod tests {
             query: HashMap::new(),
             headers: HeaderMap::new(),
             body: "".to_string(),
+            client_cert: None,
         };
         let matches = m.matches(&req);
         assert!(!matches)
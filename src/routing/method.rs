@@ -1,6 +1,8 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Method {
     Get,
@@ -12,8 +14,25 @@ pub enum Method {
     Options,
 }
 
-impl ToString for Method {
-    fn to_string(&self) -> String {
-        format!("{:?}", self).to_uppercase()
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_uppercase())
+    }
+}
+
+impl TryFrom<&axum::http::Method> for Method {
+    type Error = String;
+
+    fn try_from(value: &axum::http::Method) -> Result<Self, Self::Error> {
+        match *value {
+            axum::http::Method::GET => Ok(Method::Get),
+            axum::http::Method::POST => Ok(Method::Post),
+            axum::http::Method::PUT => Ok(Method::Put),
+            axum::http::Method::PATCH => Ok(Method::Patch),
+            axum::http::Method::DELETE => Ok(Method::Delete),
+            axum::http::Method::HEAD => Ok(Method::Head),
+            axum::http::Method::OPTIONS => Ok(Method::Options),
+            _ => Err(format!("Unsupported method {value}")),
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,14 +1,24 @@
 use crate::request::Request;
 
 pub mod body;
+pub mod builder;
 pub mod condition;
 pub mod config;
+pub mod delay;
+pub mod events;
+pub mod fault;
+pub mod format;
 pub mod matcher;
 pub mod matchers;
 pub mod method;
+pub mod pattern;
 pub mod response;
 pub mod route;
+pub mod server;
+pub(crate) mod template;
+pub mod throttle;
 pub mod value;
+pub mod websocket;
 pub mod options;
 
 trait Matching {
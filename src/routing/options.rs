@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::tls::Tls;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Options {
     pub address: String,
@@ -7,4 +9,30 @@ pub struct Options {
     pub enable_cors: Option<bool>,
     pub min_response_delay_ms: Option<u64>,
     pub max_response_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Makes random choices reproducible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+    /// How many requests the journal keeps before dropping the oldest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_capacity: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            address: String::from("localhost"),
+            port: 3003,
+            enable_cors: None,
+            min_response_delay_ms: None,
+            max_response_delay_ms: None,
+            proxy: None,
+            seed: None,
+            tls: None,
+            journal_capacity: None,
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A regular expression that is compiled once when the config is parsed.
+/// Invalid expressions are rejected during deserialization.
+#[derive(Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Pattern)
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routing::pattern::Pattern;
+
+    #[test]
+    fn test_pattern_is_compiled_on_deserialize() {
+        let pattern: Pattern = serde_yaml::from_str(r#""^Bearer [A-Za-z0-9._-]+$""#).unwrap();
+        assert!(pattern.is_match("Bearer abc.def-1"));
+        assert!(!pattern.is_match("Basic abc"));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        let pattern: Result<Pattern, _> = serde_yaml::from_str(r#""^(unclosed""#);
+        assert!(pattern.is_err());
+    }
+
+    #[test]
+    fn test_pattern_is_serialized_as_string() {
+        let pattern = Pattern::new("^[0-9]+$").unwrap();
+        let serialized = serde_yaml::to_string(&pattern).unwrap();
+        assert_eq!(serialized.trim(), "^[0-9]+$");
+    }
+}
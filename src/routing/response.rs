@@ -1,11 +1,25 @@
 use std::collections::HashMap;
-use std::io;
+use std::ops::RangeInclusive;
+use std::{fmt, io};
 
-use axum::http::{HeaderName, StatusCode};
+use axum::http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
 use axum::response::IntoResponse;
+use axum::Json;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+use crate::request::Request;
 use crate::routing::body::Body;
+use crate::routing::delay::Delay;
+use crate::routing::fault::Fault;
+use crate::routing::template;
+use crate::routing::throttle::Throttle;
+
+pub const MOCKED_ERROR: HeaderName = HeaderName::from_static("x-mocked-error");
+
+/// The statuses a response can have. HTTP only defines the classes 1xx to 5xx.
+pub const STATUSES: RangeInclusive<u16> = 100..=599;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -15,27 +29,165 @@ pub struct Response {
     pub headers: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub body: Option<Body>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub template: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_state: Option<String>,
+    /// The relative frequency of this response when picked at random.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault: Option<Fault>,
+    /// Overrides the delay of the route and the global delay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Delay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<Throttle>,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            status: StatusCode::OK.as_u16(),
+            headers: HashMap::new(),
+            body: None,
+            template: false,
+            next_state: None,
+            weight: None,
+            fault: None,
+            delay: None,
+            throttle: None,
+        }
+    }
+}
+
+/// A response that cannot be sent as configured. It is answered with a 500
+/// whose `x-mocked-error` header names the problem.
+#[derive(Debug)]
+pub enum ResponseError {
+    InvalidStatus(u16),
+    InvalidHeaderName(String),
+    InvalidHeaderValue(String),
+    Body(io::Error),
+    NoResponse,
+}
+
+impl ResponseError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ResponseError::InvalidStatus(_) => "invalid-status",
+            ResponseError::InvalidHeaderName(_) => "invalid-header-name",
+            ResponseError::InvalidHeaderValue(_) => "invalid-header-value",
+            ResponseError::Body(_) => "body",
+            ResponseError::NoResponse => "no-response",
+        }
+    }
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseError::InvalidStatus(status) => write!(
+                f,
+                "Status {status} is not between {} and {}",
+                STATUSES.start(),
+                STATUSES.end()
+            ),
+            ResponseError::InvalidHeaderName(name) => write!(f, "Invalid header name `{name}`"),
+            ResponseError::InvalidHeaderValue(name) => {
+                write!(f, "Invalid value of header `{name}`")
+            }
+            ResponseError::Body(e) => write!(f, "Unable to read body - {e}"),
+            ResponseError::NoResponse => write!(f, "Condition has no response"),
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        let body = json!({
+            "error": self.kind(),
+            "message": self.to_string(),
+        });
+        let mut resp = (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response();
+        resp.headers_mut()
+            .insert(MOCKED_ERROR, HeaderValue::from_static(self.kind()));
+        resp
+    }
 }
 
 impl Response {
-    pub fn response(self) -> axum::response::Response {
-        let status_code = StatusCode::from_u16(self.status).unwrap();
-        let result: Result<Vec<u8>, io::Error> = self.body.unwrap_or_else(Body::empty).try_into();
-        match result {
-            Ok(body) => {
-                let mut resp = (status_code, body).into_response();
-                self.headers.into_iter().for_each(|(header, value)| {
-                    let header_name: HeaderName = header.parse().unwrap();
-                    resp.headers_mut()
-                        .insert(header_name, value.parse().unwrap());
-                });
+    /// Checks what can be checked before a request arrives. Templated header
+    /// values are only known once rendered.
+    pub fn check(&self) -> Result<(), ResponseError> {
+        self.status_code()?;
+        for (name, value) in &self.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ResponseError::InvalidHeaderName(name.clone()))?;
+            if !self.template {
+                HeaderValue::from_str(value)
+                    .map_err(|_| ResponseError::InvalidHeaderValue(name.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn status_code(&self) -> Result<StatusCode, ResponseError> {
+        if !STATUSES.contains(&self.status) {
+            return Err(ResponseError::InvalidStatus(self.status));
+        }
+        StatusCode::from_u16(self.status).map_err(|_| ResponseError::InvalidStatus(self.status))
+    }
+
+    pub(crate) fn response(self, req: &Request) -> Result<axum::response::Response, ResponseError> {
+        let status_code = self.status_code()?;
+        let mut resp = match self.body {
+            Some(Body::Events(events)) => {
+                let mut resp = (status_code, events.body()).into_response();
+                let headers = resp.headers_mut();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
                 resp
             }
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error creating response: {:?}", e),
-            )
-                .into_response(),
+            body => {
+                let body: Vec<u8> = body
+                    .unwrap_or_else(Body::empty)
+                    .try_into()
+                    .map_err(ResponseError::Body)?;
+                let body = if self.template {
+                    template::render(&String::from_utf8_lossy(&body), req).into_bytes()
+                } else {
+                    body
+                };
+                match &self.throttle {
+                    Some(throttle) => {
+                        let length = body.len();
+                        let mut resp = (status_code, throttle.body(body)).into_response();
+                        // clients can still show the progress of a throttled download
+                        resp.headers_mut().insert(CONTENT_LENGTH, length.into());
+                        resp
+                    }
+                    None => (status_code, body).into_response(),
+                }
+            }
+        };
+        for (header, value) in self.headers {
+            let header_name = HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| ResponseError::InvalidHeaderName(header.clone()))?;
+            let value = if self.template {
+                template::render(&value, req)
+            } else {
+                value
+            };
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| ResponseError::InvalidHeaderValue(header.clone()))?;
+            resp.headers_mut().insert(header_name, value);
+        }
+        if let Some(fault) = self.fault {
+            resp.extensions_mut().insert(fault);
         }
+        Ok(resp)
     }
 }
@@ -1,32 +1,57 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use axum::body::Body;
-use axum::extract::{Query, RawPathParams};
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, RawPathParams, WebSocketUpgrade};
 use axum::http::header::{
     ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN
 };
-use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
 use axum::response::IntoResponse;
 use axum::routing::MethodRouter;
 use axum::{Extension, Router};
 use serde::{Deserialize, Serialize};
 
+use crate::journal::MatchedCondition;
+use crate::mocks::RouteError;
+use crate::proxy;
 use crate::request::Request;
 use crate::routing::condition::Condition;
+use crate::routing::delay::Delay;
 use crate::routing::method::Method;
+use crate::routing::options::Options;
+use crate::routing::response::{Response, ResponseError};
+use crate::routing::websocket::WebSocket;
+use crate::scenarios::Scenarios;
+use crate::state::SharedState;
+use crate::tls::ClientCert;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Route {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub path: String,
     pub methods: Vec<Method>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enable_cors: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Delay>,
+    /// Turns GET requests into WebSocket connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket: Option<WebSocket>,
+    /// Rejects requests without a client certificate with 403.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub require_client_cert: bool,
+    /// Only requests with one of these `Host` headers are answered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
     pub conditions: Vec<Condition>,
 }
 
 impl Route {
-    pub fn router(self: Arc<Self>) -> Router {
+    pub(crate) fn router(self: Arc<Self>) -> Router {
         Router::new()
             .route(self.path.as_str(), self.clone().handler_for_methods())
             .layer(Extension(self))
@@ -39,6 +64,7 @@ impl Route {
             .fold(MethodRouter::new(), |acc, method| {
                 log::info!("Adding {:?} @ {}", method, self.path);
                 match method {
+                    Method::Get if self.websocket.is_some() => acc.get(Self::websocket_handler),
                     Method::Get => acc.get(Self::handler),
                     Method::Post => acc.post(Self::handler),
                     Method::Put => acc.put(Self::handler),
@@ -56,67 +82,459 @@ impl Route {
         }
     }
 
-    async fn cors_handler(Extension(route): Extension<Arc<Route>>) -> impl IntoResponse {
+    async fn cors_handler(Extension(route): Extension<Arc<Route>>) -> axum::response::Response {
         let mut resp = (StatusCode::OK, Body::empty()).into_response();
-        let cors_headers = route.clone().cors_headers();
-        cors_headers.iter().for_each(|(k, v)| {
-            resp.headers_mut().append(k, v.clone());
-        });
+        if let Err(e) = route.add_cors_headers(&mut resp) {
+            log::error!("Unable to add CORS headers @ {} - {e}", route.path);
+            return e.into_response();
+        }
         resp
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handler(
         Extension(route): Extension<Arc<Route>>,
+        Extension(options): Extension<Options>,
+        Extension(shared): Extension<SharedState>,
+        method: axum::http::Method,
+        uri: Uri,
         headers: HeaderMap,
         path_params: RawPathParams,
         Query(query): Query<HashMap<String, String>>,
-        body: String,
+        client_cert: Option<Extension<ClientCert>>,
+        body: Bytes,
     ) -> impl IntoResponse {
-        let path_params = path_params
-            .iter()
-            .fold(HashMap::new(), |mut acc, (key, value)| {
-                acc.insert(key.to_string(), value.to_string());
-                acc
-            });
         let request = Request {
             headers,
-            path_params,
+            path_params: Self::path_params(path_params),
             query,
-            body,
+            body: String::from_utf8_lossy(&body).to_string(),
+            client_cert: client_cert.map(|Extension(cert)| cert),
         };
-        if let Some(condition) = route.clone().select_condition(&request) {
+        if let Some(resp) = route.reject_without_client_cert(&request) {
+            return resp;
+        }
+        if let Some((index, condition)) =
+            route.clone().select_condition(&request, &shared.scenarios)
+        {
             log::info!("Matched condition {:?}", condition);
-            let mut resp = condition.response.response();
+            let call = shared.calls.next(&route.call_key(index));
+            let response = condition.select_response(call, &mut *shared.rng()).cloned();
+            let Some(response) = response else {
+                log::error!("Condition {index} @ {} has no response", route.path);
+                return ResponseError::NoResponse.into_response();
+            };
+            if let Some(next_state) = &response.next_state {
+                match &condition.scenario {
+                    Some(scenario) => shared.scenarios.set(scenario, next_state),
+                    None => log::warn!("Ignoring nextState {next_state} without a scenario"),
+                }
+            }
+            let delay = response.delay.clone().or_else(|| route.delay.clone());
+            let mut resp = response.response(&request).unwrap_or_else(|e| {
+                log::error!(
+                    "Unable to respond with condition {index} @ {} - {e}",
+                    route.path
+                );
+                e.into_response()
+            });
+            if let Some(delay) = delay {
+                resp.extensions_mut().insert(delay);
+            }
+            resp.extensions_mut().insert(MatchedCondition {
+                route: route.path.clone(),
+                condition: index,
+            });
             if route.enable_cors.unwrap_or(false) {
                 log::info!("Adding CORS headers");
-                let cors_headers = route.clone().cors_headers();
-                cors_headers.iter().for_each(|(k, v)| {
-                    resp.headers_mut().append(k, v.clone());
-                });
+                if let Err(e) = route.add_cors_headers(&mut resp) {
+                    log::error!("Unable to add CORS headers @ {} - {e}", route.path);
+                    return e.into_response();
+                }
             }
             resp
+        } else if let Some(upstream) = route.proxy.as_ref().or(options.proxy.as_ref()) {
+            log::info!("Unable to select response, forwarding to {upstream}");
+            proxy::forward(upstream, method, &uri, request.headers, body)
+                .await
+                .into_response()
         } else {
             log::warn!("Unable to select response");
             (StatusCode::NOT_FOUND, "Unable to select response for input").into_response()
         }
     }
 
-    fn cors_headers(self: Arc<Route>) -> HeaderMap<HeaderValue> {
+    async fn websocket_handler(
+        Extension(route): Extension<Arc<Route>>,
+        upgrade: WebSocketUpgrade,
+        headers: HeaderMap,
+        path_params: RawPathParams,
+        Query(query): Query<HashMap<String, String>>,
+        client_cert: Option<Extension<ClientCert>>,
+    ) -> axum::response::Response {
+        let handshake = Request {
+            headers,
+            path_params: Self::path_params(path_params),
+            query,
+            body: String::new(),
+            client_cert: client_cert.map(|Extension(cert)| cert),
+        };
+        if let Some(resp) = route.reject_without_client_cert(&handshake) {
+            return resp;
+        }
+        let websocket = route.websocket.clone().unwrap_or_default();
+        log::info!("Upgrading to WebSocket @ {}", route.path);
+        upgrade
+            .on_upgrade(move |socket| websocket.session(socket, handshake))
+            .into_response()
+    }
+
+    fn reject_without_client_cert(&self, req: &Request) -> Option<axum::response::Response> {
+        if !self.require_client_cert || req.client_cert.is_some() {
+            return None;
+        }
+        log::warn!(
+            "Rejecting request without client certificate @ {}",
+            self.path
+        );
+        Some((StatusCode::FORBIDDEN, "Client certificate required").into_response())
+    }
+
+    fn path_params(path_params: RawPathParams) -> HashMap<String, String> {
+        path_params
+            .iter()
+            .fold(HashMap::new(), |mut acc, (key, value)| {
+                acc.insert(key.to_string(), value.to_string());
+                acc
+            })
+    }
+
+    fn add_cors_headers(&self, resp: &mut axum::response::Response) -> Result<(), ResponseError> {
         let methods = self
             .methods
             .iter()
             .map(|m| m.to_string())
             .collect::<Vec<String>>()
             .join(",");
-        let mut headers = HeaderMap::new();
-        headers.append(ACCESS_CONTROL_ALLOW_ORIGIN, "*".parse().unwrap());
-        headers.append(ACCESS_CONTROL_ALLOW_METHODS, methods.parse().unwrap());
-        headers.append(ACCESS_CONTROL_ALLOW_HEADERS, "Authorization".parse().unwrap());
-        headers.append(ACCESS_CONTROL_ALLOW_CREDENTIALS, "true".parse().unwrap());
-        headers
+        let methods = HeaderValue::from_str(&methods).map_err(|_| {
+            ResponseError::InvalidHeaderValue(ACCESS_CONTROL_ALLOW_METHODS.to_string())
+        })?;
+        let headers = resp.headers_mut();
+        headers.append(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        headers.append(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        headers.append(
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static("Authorization"),
+        );
+        headers.append(
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+        Ok(())
+    }
+
+    /// Checks the path, the methods and the responses, so that serving the
+    /// route cannot fail on them. `enable_cors` has to be inherited from the
+    /// options beforehand, see [`Config::inherit_enable_cors_for`].
+    ///
+    /// [`Config::inherit_enable_cors_for`]: crate::routing::config::Config::inherit_enable_cors_for
+    pub fn check(&self) -> Result<(), RouteError> {
+        if let Some(reason) = check_path(&self.path) {
+            return Err(RouteError::InvalidPath {
+                path: self.path.clone(),
+                reason,
+            });
+        }
+        if let Some((_, method)) = self.duplicate_method() {
+            return Err(RouteError::DuplicateMethod {
+                path: self.path.clone(),
+                method,
+            });
+        }
+        if self.enable_cors.unwrap_or(false) && self.methods.contains(&Method::Options) {
+            return Err(RouteError::OptionsWithCors(self.path.clone()));
+        }
+        self.conditions
+            .iter()
+            .flat_map(|c| c.response.iter().chain(c.responses.iter()))
+            .try_for_each(Response::check)
+            .map_err(|error| RouteError::Response {
+                path: self.path.clone(),
+                error,
+            })
+    }
+
+    /// Returns the first method both routes respond to if they share the same
+    /// path. Such routes cannot be served side by side.
+    pub fn conflicts_with(&self, other: &Route) -> Option<Method> {
+        if normalize_path(&self.path) != normalize_path(&other.path) {
+            return None;
+        }
+        // routes for different hosts are served by different routers
+        let shared_host = self.hosts.iter().any(|h| other.serves_host(h));
+        if (!self.hosts.is_empty() || !other.hosts.is_empty()) && !shared_host {
+            return None;
+        }
+        let methods = other.served_methods();
+        self.served_methods()
+            .into_iter()
+            .find(|m| methods.contains(m))
+    }
+
+    /// axum refuses to add a handler for the same method twice. Returns the
+    /// first repetition and its index in `methods`.
+    pub(crate) fn duplicate_method(&self) -> Option<(usize, Method)> {
+        self.methods
+            .iter()
+            .enumerate()
+            .find(|(index, m)| self.methods[..*index].contains(m))
+            .map(|(index, m)| (index, *m))
+    }
+
+    /// Host names are compared without port and case.
+    pub fn serves_host(&self, host: &str) -> bool {
+        self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host))
+    }
+
+    fn served_methods(&self) -> Vec<Method> {
+        let mut methods = self.methods.clone();
+        if self.enable_cors.unwrap_or(false) {
+            methods.push(Method::Options);
+        }
+        methods
+    }
+
+    /// Identifies a condition of this route when counting its calls.
+    fn call_key(&self, index: usize) -> String {
+        format!("{}#{index}", self.id.as_deref().unwrap_or(&self.path))
+    }
+
+    fn select_condition(
+        self: Arc<Route>,
+        req: &Request,
+        scenarios: &Scenarios,
+    ) -> Option<(usize, Condition)> {
+        self.conditions
+            .iter()
+            .enumerate()
+            .find(|(_, r)| r.in_state(scenarios) && r.matches(req))
+            .map(|(index, r)| (index, r.clone()))
+    }
+}
+
+/// Catches paths axum would refuse when building the router.
+pub(crate) fn check_path(path: &str) -> Option<String> {
+    if !path.starts_with('/') {
+        return Some(format!("Path {path} has to start with /"));
+    }
+    let segments: Vec<&str> = path.split('/').collect();
+    for (index, segment) in segments.iter().enumerate() {
+        if let Some(name) = segment.strip_prefix(':') {
+            return Some(format!(
+                "Path params are written as {{{name}}}, not {segment}"
+            ));
+        }
+        if let Some(name) = segment.strip_prefix('*') {
+            return Some(format!(
+                "Wildcards are written as {{*{name}}}, not {segment}"
+            ));
+        }
+        if segment.matches('{').count() != segment.matches('}').count() {
+            return Some(format!("Unbalanced braces in {segment}"));
+        }
+        if segment.starts_with("{*") && index != segments.len() - 1 {
+            return Some(format!("Wildcard {segment} has to be the last segment"));
+        }
+    }
+    None
+}
+
+/// Path params only differ by name, which does not make two paths distinct.
+fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with('{') && segment.ends_with('}') {
+                if segment.starts_with("{*") {
+                    "{*}"
+                } else {
+                    "{}"
+                }
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+
+    use crate::mocks::RouteError;
+    use crate::routing::config::Config;
+    use crate::routing::method::Method;
+    use crate::routing::response::{ResponseError, MOCKED_ERROR};
+    use crate::routing::route::Route;
+    use crate::state::SharedState;
+
+    fn route(path: &str, methods: &str, enable_cors: bool) -> Route {
+        serde_yaml::from_str(&format!(
+            r#"
+            path: {path}
+            methods: [{methods}]
+            enable_cors: {enable_cors}
+            conditions: []
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_routes_with_same_path_and_method_conflict() {
+        let a = route("/v1/{id}", "GET, POST", false);
+        let b = route("/v1/{name}", "POST", false);
+        assert!(a.conflicts_with(&b).is_some());
+    }
+
+    #[test]
+    fn test_routes_with_different_methods_do_not_conflict() {
+        let a = route("/v1/{id}", "GET", false);
+        let b = route("/v1/{id}", "POST", false);
+        assert!(a.conflicts_with(&b).is_none());
+        let c = route("/v1/{id}/items", "GET", false);
+        assert!(a.conflicts_with(&c).is_none());
+    }
+
+    #[test]
+    fn test_routes_for_different_hosts_do_not_conflict() {
+        let mut a = route("/v1", "GET", false);
+        let mut b = route("/v1", "GET", false);
+        a.hosts = vec![String::from("payments.local")];
+        assert!(a.conflicts_with(&b).is_none());
+        b.hosts = vec![String::from("users.local")];
+        assert!(a.conflicts_with(&b).is_none());
+        b.hosts.push(String::from("Payments.local"));
+        assert!(a.conflicts_with(&b).is_some());
+    }
+
+    #[test]
+    fn test_cors_routes_conflict_on_options() {
+        let a = route("/v1", "GET", true);
+        let b = route("/v1", "POST", true);
+        assert!(a.conflicts_with(&b).is_some());
+    }
+
+    #[test]
+    fn test_duplicate_method_is_rejected() {
+        let result = route("/v1", "GET, POST, GET", false).check();
+        assert!(matches!(
+            result,
+            Err(RouteError::DuplicateMethod {
+                method: Method::Get,
+                ..
+            })
+        ));
+        assert!(route("/v1", "GET, HEAD", false).check().is_ok());
+    }
+
+    #[test]
+    fn test_status_outside_of_http_classes_is_rejected() {
+        let mut route = route("/v1", "GET", false);
+        route.conditions = serde_yaml::from_str("[response: {status: 600}]").unwrap();
+        assert!(matches!(
+            route.check(),
+            Err(RouteError::Response {
+                error: ResponseError::InvalidStatus(600),
+                ..
+            })
+        ));
+        route.conditions[0].response.as_mut().unwrap().status = 599;
+        assert!(route.check().is_ok());
+    }
+
+    #[test]
+    fn test_options_with_cors_is_rejected() {
+        let result = route("/v1", "GET, OPTIONS", true).check();
+        assert!(matches!(result, Err(RouteError::OptionsWithCors(_))));
+        assert!(route("/v1", "GET, OPTIONS", false).check().is_ok());
+    }
+
+    #[test]
+    fn test_options_with_inherited_cors_cannot_be_served() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+              enable_cors: true
+            routes:
+              - path: /v1
+                methods: [OPTIONS]
+                conditions: []
+            "#,
+        )
+        .unwrap();
+        let result = config.router(&SharedState::default());
+        assert!(matches!(result, Err(RouteError::OptionsWithCors(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_and_head_are_served_side_by_side() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /v1
+                methods: [GET, HEAD]
+                conditions:
+                  - response:
+                      status: 200
+            "#,
+        )
+        .unwrap();
+        let server = TestServer::new(config.router(&SharedState::default()).unwrap()).unwrap();
+        server
+            .method(axum::http::Method::HEAD, "/v1")
+            .await
+            .assert_status_ok();
     }
 
-    fn select_condition(self: Arc<Route>, req: &Request) -> Option<Condition> {
-        self.conditions.iter().cloned().find(|r| r.matches(&req))
+    #[tokio::test]
+    async fn test_response_that_cannot_be_sent_is_a_500() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /echo
+                methods:
+                  - POST
+                conditions:
+                  - response:
+                      status: 200
+                      template: true
+                      headers:
+                        X-Echo: "{{body}}"
+            "#,
+        )
+        .unwrap();
+        let server = TestServer::new(config.router(&SharedState::default()).unwrap()).unwrap();
+        server
+            .post("/echo")
+            .text("one line")
+            .await
+            .assert_status_ok();
+        let resp = server.post("/echo").text("two\nlines").await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.header(MOCKED_ERROR), "invalid-header-value");
+        resp.assert_json(&serde_json::json!({
+            "error": "invalid-header-value",
+            "message": "Invalid value of header `X-Echo`",
+        }));
     }
 }
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::routing::route::Route;
+use crate::tls::Tls;
+
+/// A further server in the same config. It either listens on a port of its
+/// own or shares the port of the options and is selected by the Host header.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Server {
+    /// Defaults to the address of the options.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Defaults to the port of the options.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Only requests for these hosts are answered, unless a route sets its own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// Not inherited from the options, every port decides on its own. Servers
+    /// sharing a port either leave it out or repeat the one of that port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+    pub routes: Vec<Route>,
+}
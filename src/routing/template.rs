@@ -0,0 +1,136 @@
+use chrono::Utc;
+use rand::Rng;
+use serde_json_path::JsonPath;
+use uuid::Uuid;
+
+use crate::request::Request;
+
+/// Renders all `{{ expression }}` placeholders of `template` using the data of
+/// the incoming request. Unknown expressions and missing values render as an
+/// empty string.
+pub fn render(template: &str, req: &Request) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let expression = rest[start + 2..start + 2 + end].trim();
+        rendered.push_str(&evaluate(expression, req).unwrap_or_default());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn evaluate(expression: &str, req: &Request) -> Option<String> {
+    let mut parts = expression.split_whitespace();
+    let name = parts.next()?;
+    let args: Vec<&str> = parts.collect();
+    match name {
+        "now" => Some(Utc::now().to_rfc3339()),
+        "uuid" => Some(Uuid::new_v4().to_string()),
+        "randomInt" => random_int(&args),
+        "body" => Some(req.body.clone()),
+        _ => {
+            if name.starts_with("body.json.") {
+                // the JSONPath itself may contain spaces, e.g. in filters
+                json_path(&expression["body.json.".len()..], req)
+            } else if let Some(param) = name.strip_prefix("path.") {
+                req.path_params.get(param).cloned()
+            } else if let Some(param) = name.strip_prefix("query.") {
+                req.query.get(param).cloned()
+            } else if let Some(header) = name.strip_prefix("headers.") {
+                req.headers
+                    .get(header)
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from)
+            } else {
+                log::warn!("Unknown template expression: {expression}");
+                None
+            }
+        }
+    }
+}
+
+fn random_int(args: &[&str]) -> Option<String> {
+    let min = args.first().and_then(|v| v.parse().ok()).unwrap_or(0);
+    let max = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(100);
+    if min > max {
+        log::warn!("Invalid range for randomInt: {min} > {max}");
+        return None;
+    }
+    Some(rand::rng().random_range(min..=max).to_string())
+}
+
+fn json_path(path: &str, req: &Request) -> Option<String> {
+    let path = JsonPath::parse(path.trim()).ok()?;
+    let body: serde_json::Value = serde_json::from_str(&req.body).ok()?;
+    let node = path.query(&body).first()?;
+    match node {
+        serde_json::Value::String(v) => Some(v.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::HeaderMap;
+
+    use crate::request::Request;
+    use crate::routing::template::render;
+
+    fn request() -> Request {
+        let mut path_params = HashMap::new();
+        path_params.insert(String::from("id"), String::from("42"));
+        let mut query = HashMap::new();
+        query.insert(String::from("page"), String::from("3"));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc".parse().unwrap());
+        Request {
+            path_params,
+            query,
+            headers,
+            body: String::from(r#"{"name": "mocked", "tags": ["a", "b"]}"#),
+            client_cert: None,
+        }
+    }
+
+    #[test]
+    fn test_render_request_data() {
+        let rendered = render(
+            "{{path.id}}/{{ query.page }}/{{headers.X-Request-Id}}/{{body.json.$.name}}",
+            &request(),
+        );
+        assert_eq!(rendered, "42/3/abc/mocked");
+    }
+
+    #[test]
+    fn test_render_json_node() {
+        let rendered = render("{{body.json.$.tags}}", &request());
+        assert_eq!(rendered, r#"["a","b"]"#);
+    }
+
+    #[test]
+    fn test_missing_values_render_empty() {
+        let rendered = render("[{{path.missing}}][{{unknown}}]", &request());
+        assert_eq!(rendered, "[][]");
+    }
+
+    #[test]
+    fn test_unclosed_placeholder_is_kept() {
+        let rendered = render("{{path.id}} {{path.id", &request());
+        assert_eq!(rendered, "42 {{path.id");
+    }
+
+    #[test]
+    fn test_helpers() {
+        let value: i64 = render("{{randomInt 5 7}}", &request()).parse().unwrap();
+        assert!((5..=7).contains(&value));
+        assert_eq!(render("{{uuid}}", &request()).len(), 36);
+        assert!(chrono::DateTime::parse_from_rfc3339(&render("{{now}}", &request())).is_ok());
+    }
+}
@@ -0,0 +1,94 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+/// The number of chunks sent per second if only a throughput is given.
+const CHUNKS_PER_SEC: u64 = 10;
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Streams the body in chunks instead of sending it at once.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Throttle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput_bytes_per_sec: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_delay_ms: Option<u64>,
+}
+
+impl Throttle {
+    /// The size of each chunk and the pause between two chunks. A throughput
+    /// takes precedence over `chunk_delay_ms`.
+    fn plan(&self) -> (usize, Duration) {
+        match self.throughput_bytes_per_sec.filter(|t| *t > 0) {
+            Some(throughput) => {
+                let size = self
+                    .chunk_size
+                    .unwrap_or((throughput / CHUNKS_PER_SEC).max(1) as usize)
+                    .max(1);
+                let delay = Duration::from_secs_f64(size as f64 / throughput as f64);
+                (size, delay)
+            }
+            None => (
+                self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1),
+                Duration::from_millis(self.chunk_delay_ms.unwrap_or(0)),
+            ),
+        }
+    }
+
+    pub fn body(&self, body: Vec<u8>) -> Body {
+        let (size, delay) = self.plan();
+        let chunks: Vec<Bytes> = body.chunks(size).map(Bytes::copy_from_slice).collect();
+        let chunks =
+            stream::iter(chunks.into_iter().enumerate()).then(move |(index, chunk)| async move {
+                if index > 0 {
+                    tokio::time::sleep(delay).await;
+                }
+                Ok::<_, Infallible>(chunk)
+            });
+        Body::from_stream(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::routing::throttle::Throttle;
+
+    #[test]
+    fn test_throughput_is_split_into_chunks() {
+        let throttle = Throttle {
+            throughput_bytes_per_sec: Some(2048),
+            ..Default::default()
+        };
+        assert_eq!(
+            throttle.plan(),
+            (204, Duration::from_secs_f64(204.0 / 2048.0))
+        );
+        let throttle = Throttle {
+            chunk_size: Some(16),
+            chunk_delay_ms: Some(250),
+            ..Default::default()
+        };
+        assert_eq!(throttle.plan(), (16, Duration::from_millis(250)));
+    }
+
+    #[tokio::test]
+    async fn test_body_is_streamed_slowly() {
+        let throttle = Throttle {
+            chunk_size: Some(2),
+            chunk_delay_ms: Some(50),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let body = throttle.body(b"abcdef".to_vec());
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"abcdef");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -1,13 +1,43 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[serde(untagged)]
+#[serde(untagged, from = "Scalar")]
 pub enum Value {
     String(String),
     Integer(i64),
     Numeric(f64),
 }
 
+/// Any scalar of a config. Booleans and `null` are read as their string form,
+/// just like [`Value::from_json`] does for request bodies.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Numeric(f64),
+    Bool(bool),
+    Null,
+}
+
+impl From<Scalar> for Value {
+    fn from(value: Scalar) -> Self {
+        match value {
+            Scalar::String(v) => Self::String(v),
+            Scalar::Integer(v) => Self::Integer(v),
+            Scalar::Numeric(v) => Self::Numeric(v),
+            Scalar::Bool(v) => Self::String(v.to_string()),
+            Scalar::Null => Self::String(String::from("null")),
+        }
+    }
+}
+
+/// Reads an optional value where `null` is a value of its own rather than
+/// the absence of one.
+pub(crate) fn some<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
 impl From<String> for Value {
     fn from(value: String) -> Self {
         if let Ok(v) = value.parse::<i64>() {
@@ -20,8 +50,51 @@ impl From<String> for Value {
     }
 }
 
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        value.to_string().into()
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Numeric(value)
+    }
+}
+
+impl Value {
+    /// Converts a JSON node into a `Value`. Booleans and `null` are
+    /// represented by their string form so they can still be compared.
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::String(v) => Some(Self::String(v.clone())),
+            serde_json::Value::Number(v) => v
+                .as_i64()
+                .map(Self::Integer)
+                .or_else(|| v.as_f64().map(Self::Numeric)),
+            serde_json::Value::Bool(v) => Some(Self::String(v.to_string())),
+            serde_json::Value::Null => Some(Self::String(String::from("null"))),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use crate::routing::value::Value;
 
     #[test]
@@ -42,4 +115,33 @@ mod tests {
         let v: Value = String::from("hello").into();
         assert_eq!(v, Value::String(String::from("hello")));
     }
+
+    #[test]
+    fn test_from_json() {
+        assert_eq!(Value::from_json(&json!(0)), Some(Value::Integer(0)));
+        assert_eq!(Value::from_json(&json!(1.5)), Some(Value::Numeric(1.5)));
+        assert_eq!(
+            Value::from_json(&json!("foo")),
+            Some(Value::String(String::from("foo")))
+        );
+        assert_eq!(
+            Value::from_json(&json!(true)),
+            Some(Value::String(String::from("true")))
+        );
+        assert_eq!(Value::from_json(&json!({"a": 1})), None);
+    }
+
+    #[test]
+    fn test_bool_and_null_are_read_as_strings() {
+        let values: Vec<Value> = serde_yaml::from_str("[true, null, 'false', 1]").unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Value::String(String::from("true")),
+                Value::String(String::from("null")),
+                Value::String(String::from("false")),
+                Value::Integer(1),
+            ]
+        );
+    }
 }
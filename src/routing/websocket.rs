@@ -0,0 +1,264 @@
+use std::time::Duration;
+
+use axum::extract::ws::{self, CloseFrame};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinSet;
+
+use crate::request::Request;
+use crate::routing::condition::{deserialize_matcher, deserialize_matchers};
+use crate::routing::matcher::Matcher;
+use crate::routing::matchers::Matchers;
+
+/// A scripted WebSocket endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct WebSocket {
+    /// Sent once the connection is established.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_connect: Vec<Message>,
+    /// Answers to incoming messages. The first reply that matches is used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<Reply>,
+    /// Messages sent by the server on its own, e.g. periodic updates.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pushes: Vec<Push>,
+    /// Closes the connection after `after_ms`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close: Option<Close>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Message {
+    pub text: String,
+    /// The time to wait before the message is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+}
+
+/// Matchers are evaluated against a request made of the handshake and the
+/// text of the incoming message as body.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Reply {
+    #[serde(flatten, deserialize_with = "deserialize_matcher")]
+    pub matcher: Option<Matcher>,
+    #[serde(flatten, deserialize_with = "deserialize_matchers")]
+    pub matchers: Option<Matchers>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    /// Closes the connection after the messages were sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close: Option<Close>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Push {
+    pub text: String,
+    #[serde(default)]
+    pub after_ms: u64,
+    /// Repeats the message in this interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Close {
+    #[serde(default = "normal_closure")]
+    pub code: u16,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_ms: Option<u64>,
+}
+
+fn normal_closure() -> u16 {
+    1000
+}
+
+enum Outgoing {
+    Text(String),
+    Close(Close),
+}
+
+impl Reply {
+    fn matches(&self, req: &Request) -> bool {
+        match (&self.matcher, &self.matchers) {
+            (Some(matcher), _) => matcher.matches(req),
+            (None, Some(matchers)) => matchers.matches(req),
+            (None, None) => true,
+        }
+    }
+}
+
+impl WebSocket {
+    /// Runs the script until either side closes the connection.
+    pub(crate) async fn session(self, mut socket: ws::WebSocket, handshake: Request) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        // all scripted tasks are aborted once the session ends
+        let mut tasks = JoinSet::new();
+        tasks.spawn(send(self.on_connect.clone(), None, tx.clone()));
+        self.pushes.iter().cloned().for_each(|push| {
+            tasks.spawn(push_periodically(push, tx.clone()));
+        });
+        if let Some(close) = self.close.clone() {
+            tasks.spawn(send(vec![], Some(close), tx.clone()));
+        }
+        loop {
+            tokio::select! {
+                incoming = socket.recv() => match incoming {
+                    Some(Ok(ws::Message::Text(text))) => {
+                        let request = Request {
+                            body: text.to_string(),
+                            ..handshake.clone()
+                        };
+                        match self.replies.iter().find(|r| r.matches(&request)) {
+                            Some(reply) => {
+                                let messages = reply.messages.clone();
+                                tasks.spawn(send(messages, reply.close.clone(), tx.clone()));
+                            }
+                            None => log::info!("No reply for message {text}"),
+                        }
+                    }
+                    Some(Ok(ws::Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        log::warn!("WebSocket error - {e}");
+                        break;
+                    }
+                },
+                Some(outgoing) = rx.recv() => match outgoing {
+                    Outgoing::Text(text) => {
+                        if socket.send(ws::Message::Text(text.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Outgoing::Close(close) => {
+                        let frame = CloseFrame {
+                            code: close.code,
+                            reason: close.reason.into(),
+                        };
+                        let _ = socket.send(ws::Message::Close(Some(frame))).await;
+                        break;
+                    }
+                },
+            }
+        }
+    }
+}
+
+async fn send(messages: Vec<Message>, close: Option<Close>, tx: UnboundedSender<Outgoing>) {
+    for message in messages {
+        sleep(message.delay_ms).await;
+        let _ = tx.send(Outgoing::Text(message.text));
+    }
+    if let Some(close) = close {
+        sleep(close.after_ms).await;
+        let _ = tx.send(Outgoing::Close(close));
+    }
+}
+
+async fn push_periodically(push: Push, tx: UnboundedSender<Outgoing>) {
+    sleep(Some(push.after_ms)).await;
+    loop {
+        if tx.send(Outgoing::Text(push.text.clone())).is_err() {
+            return;
+        }
+        match push.every_ms {
+            Some(every) => sleep(Some(every.max(1))).await,
+            None => return,
+        }
+    }
+}
+
+async fn sleep(ms: Option<u64>) {
+    if let Some(ms) = ms.filter(|ms| *ms > 0) {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_test::TestServer;
+
+    use crate::routing::config::Config;
+    use crate::state::SharedState;
+
+    fn server() -> TestServer {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /ws/{room}
+                methods:
+                  - GET
+                websocket:
+                  on_connect:
+                    - text: welcome
+                  replies:
+                    - type: BodyContains
+                      with:
+                        values:
+                          - ping
+                      messages:
+                        - text: pong
+                    - type: BodyJsonPath
+                      with:
+                        path: $.action
+                        equals: join
+                      messages:
+                        - text: joined
+                          delay_ms: 10
+                    - type: BodyContains
+                      with:
+                        values:
+                          - bye
+                      close:
+                        code: 4000
+                        reason: bye
+                  pushes:
+                    - text: tick
+                      after_ms: 100
+                conditions: []
+            "#,
+        )
+        .unwrap();
+        TestServer::builder()
+            .http_transport()
+            .build(config.router(&SharedState::default()).unwrap())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_replied() {
+        let server = server();
+        let mut socket = server
+            .get_websocket("/ws/lobby")
+            .await
+            .into_websocket()
+            .await;
+        socket.assert_receive_text("welcome").await;
+        socket.send_text("ping").await;
+        socket.assert_receive_text("pong").await;
+        socket.send_text(r#"{"action": "join"}"#).await;
+        socket.assert_receive_text("joined").await;
+        socket.assert_receive_text("tick").await;
+    }
+
+    #[tokio::test]
+    async fn test_reply_closes_connection() {
+        let server = server();
+        let mut socket = server
+            .get_websocket("/ws/lobby")
+            .await
+            .into_websocket()
+            .await;
+        socket.assert_receive_text("welcome").await;
+        socket.send_text("bye").await;
+        let message = socket.receive_message().await;
+        let axum_test::WsMessage::Close(Some(frame)) = message else {
+            panic!("Expected a close frame, got {message:?}");
+        };
+        assert_eq!(u16::from(frame.code), 4000);
+    }
+}
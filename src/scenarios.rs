@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The state every scenario is in until a response moves it on.
+pub const STARTED: &str = "started";
+
+/// The current state of every scenario.
+#[derive(Clone, Debug, Default)]
+pub struct Scenarios {
+    states: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Scenarios {
+    pub fn state(&self, scenario: &str) -> String {
+        self.states
+            .lock()
+            .unwrap()
+            .get(scenario)
+            .cloned()
+            .unwrap_or_else(|| String::from(STARTED))
+    }
+
+    pub fn set(&self, scenario: &str, state: &str) {
+        log::info!("Scenario {scenario} is now in state {state}");
+        self.states
+            .lock()
+            .unwrap()
+            .insert(scenario.to_string(), state.to_string());
+    }
+
+    pub fn reset(&self, scenario: &str) {
+        self.states.lock().unwrap().remove(scenario);
+    }
+
+    pub fn reset_all(&self) {
+        self.states.lock().unwrap().clear();
+    }
+
+    /// The states of the given scenarios and all scenarios that left their initial state.
+    pub fn states<'a>(&self, scenarios: impl Iterator<Item = &'a str>) -> HashMap<String, String> {
+        let mut states = self.states.lock().unwrap().clone();
+        scenarios.for_each(|scenario| {
+            states
+                .entry(scenario.to_string())
+                .or_insert_with(|| String::from(STARTED));
+        });
+        states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenarios::{Scenarios, STARTED};
+
+    #[test]
+    fn test_scenarios_start_in_started() {
+        let scenarios = Scenarios::default();
+        assert_eq!(scenarios.state("checkout"), STARTED);
+        scenarios.set("checkout", "paid");
+        assert_eq!(scenarios.state("checkout"), "paid");
+        scenarios.reset("checkout");
+        assert_eq!(scenarios.state("checkout"), STARTED);
+    }
+
+    #[test]
+    fn test_states_include_known_scenarios() {
+        let scenarios = Scenarios::default();
+        scenarios.set("checkout", "paid");
+        let states = scenarios.states(["login"].into_iter());
+        assert_eq!(states.get("checkout").unwrap(), "paid");
+        assert_eq!(states.get("login").unwrap(), STARTED);
+        scenarios.reset_all();
+        assert_eq!(scenarios.state("checkout"), STARTED);
+    }
+}
@@ -0,0 +1,329 @@
+use std::future::{self, Future};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use axum::http::Version;
+use axum::response::Response;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use socket2::SockRef;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::routing::fault::Fault;
+use crate::tls::ClientCert;
+
+const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Serves the router like `axum::serve`, but keeps access to each connection
+/// so that faults can be applied to it. Connections are encrypted if an
+/// acceptor is given.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    tls: Option<TlsAcceptor>,
+) -> io::Result<()> {
+    serve_until(listener, router, tls, future::pending()).await
+}
+
+/// Like [`serve`], until `shutdown` completes. Then the listener is closed and
+/// so are all open connections, keep-alive ones included.
+pub async fn serve_until(
+    listener: TcpListener,
+    router: Router,
+    tls: Option<TlsAcceptor>,
+    shutdown: impl Future<Output = ()>,
+) -> io::Result<()> {
+    let mut connections = JoinSet::new();
+    tokio::pin!(shutdown);
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = &mut shutdown => break,
+        };
+        let (stream, addr) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // e.g. too many open files, retrying right away would spin
+                log::error!("Failed to accept connection - {e}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        // closed connections are not kept around
+        while connections.try_join_next().is_some() {}
+        let router = router.clone();
+        let tls = tls.clone();
+        connections.spawn(async move {
+            if let Err(e) = connection(stream, router, tls).await {
+                log::debug!("Connection from {addr} closed - {e}");
+            }
+        });
+    }
+    connections.shutdown().await;
+    Ok(())
+}
+
+async fn connection(
+    stream: TcpStream,
+    router: Router,
+    tls: Option<TlsAcceptor>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let stream = stream.into_std()?;
+    // a second handle to the socket that stays usable while hyper owns the stream
+    let raw = Arc::new(stream.try_clone()?);
+    let stream = TcpStream::from_std(stream)?;
+    match tls {
+        Some(acceptor) => {
+            let stream = acceptor.accept(stream).await?;
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| ClientCert::from_der(cert));
+            serve_connection(stream, router, raw, true, client_cert).await
+        }
+        None => serve_connection(stream, router, raw, false, None).await,
+    }
+}
+
+async fn serve_connection<S>(
+    stream: S,
+    router: Router,
+    raw: Arc<std::net::TcpStream>,
+    encrypted: bool,
+    client_cert: Option<ClientCert>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |mut req: hyper::Request<Incoming>| {
+        let router = router.clone();
+        let raw = raw.clone();
+        if let Some(client_cert) = &client_cert {
+            req.extensions_mut().insert(client_cert.clone());
+        }
+        async move {
+            // raw writes would bypass TLS and break the framing of HTTP/2
+            let plain = !encrypted && req.version() != Version::HTTP_2;
+            let Ok(resp) = router.oneshot(req.map(Body::new)).await;
+            match resp.extensions().get::<Fault>().copied() {
+                Some(fault) => Err(inject(fault, plain, resp, &raw).await),
+                None => Ok(resp),
+            }
+        }
+    });
+    Builder::new(TokioExecutor::new())
+        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+        .await
+}
+
+/// Applies the fault to the connection. The returned error makes hyper close
+/// the connection without sending a response of its own.
+async fn inject(fault: Fault, plain: bool, resp: Response, raw: &std::net::TcpStream) -> io::Error {
+    log::info!("Injecting fault {fault:?}");
+    let result = match fault {
+        Fault::Hang => future::pending().await,
+        Fault::ConnectionReset => SockRef::from(raw).set_linger(Some(Duration::ZERO)),
+        Fault::EmptyReply => Ok(()),
+        // the connection is closed instead if raw writes are not possible
+        Fault::TruncatedBody | Fault::MalformedChunked if !plain => Ok(()),
+        Fault::TruncatedBody | Fault::MalformedChunked => write_raw(fault, resp, raw).await,
+    };
+    match result {
+        Ok(()) => io::Error::other(format!("injected {fault:?}")),
+        Err(e) => e,
+    }
+}
+
+async fn write_raw(fault: Fault, resp: Response, raw: &std::net::TcpStream) -> io::Result<()> {
+    let (parts, body) = resp.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(io::Error::other)?;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        parts.status.as_u16(),
+        parts.status.canonical_reason().unwrap_or_default()
+    );
+    parts
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != CONTENT_LENGTH && *name != TRANSFER_ENCODING)
+        .for_each(|(name, value)| {
+            head.push_str(&format!(
+                "{name}: {}\r\n",
+                String::from_utf8_lossy(value.as_bytes())
+            ));
+        });
+    let bytes = match fault {
+        Fault::TruncatedBody => {
+            head.push_str(&format!("{CONTENT_LENGTH}: {}\r\n\r\n", body.len()));
+            let mut bytes = head.into_bytes();
+            bytes.extend_from_slice(&body[..body.len() / 2]);
+            bytes
+        }
+        _ => {
+            // the chunk is announced one byte longer than it is
+            head.push_str(&format!("{TRANSFER_ENCODING}: chunked\r\n\r\n"));
+            let mut bytes = head.into_bytes();
+            bytes.extend_from_slice(format!("{:x}\r\n", body.len() + 1).as_bytes());
+            bytes.extend_from_slice(&body);
+            bytes.extend_from_slice(b"\r\n0\r\n\r\n");
+            bytes
+        }
+    };
+    let mut stream = TcpStream::from_std(raw.try_clone()?)?;
+    stream.write_all(&bytes).await?;
+    stream.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::routing::config::Config;
+    use crate::server::serve;
+    use crate::state::SharedState;
+
+    async fn server() -> String {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /{fault}
+                methods:
+                  - GET
+                conditions:
+                  - type: PathParam
+                    with:
+                      name: fault
+                      values:
+                        - connection_reset
+                    response:
+                      status: 200
+                      fault: connection_reset
+                  - type: PathParam
+                    with:
+                      name: fault
+                      values:
+                        - empty_reply
+                    response:
+                      status: 200
+                      fault: empty_reply
+                  - type: PathParam
+                    with:
+                      name: fault
+                      values:
+                        - truncated_body
+                    response:
+                      status: 200
+                      body: 0123456789
+                      fault: truncated_body
+                  - type: PathParam
+                    with:
+                      name: fault
+                      values:
+                        - malformed_chunked
+                    response:
+                      status: 200
+                      body: hello
+                      fault: malformed_chunked
+                  - type: PathParam
+                    with:
+                      name: fault
+                      values:
+                        - hang
+                    response:
+                      status: 200
+                      fault: hang
+                  - response:
+                      status: 200
+                      body: ok
+            "#,
+        )
+        .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(serve(
+            listener,
+            config.router(&SharedState::default()).unwrap(),
+            None,
+        ));
+        addr
+    }
+
+    /// Sends a GET request and reads until the server closes the connection.
+    async fn get(addr: &str, path: &str) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await?;
+        let mut resp = Vec::new();
+        stream.read_to_end(&mut resp).await?;
+        Ok(String::from_utf8_lossy(&resp).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_responses_without_fault_are_served() {
+        let addr = server().await;
+        let resp = get(&addr, "/none").await.unwrap();
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+        assert!(resp.ends_with("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_is_reset() {
+        let addr = server().await;
+        let err = get(&addr, "/connection_reset").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn test_empty_reply() {
+        let addr = server().await;
+        assert_eq!(get(&addr, "/empty_reply").await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_body_is_truncated() {
+        let addr = server().await;
+        let resp = get(&addr, "/truncated_body").await.unwrap();
+        assert!(resp.contains("content-length: 10\r\n"));
+        assert!(resp.ends_with("\r\n\r\n01234"));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_size_is_wrong() {
+        let addr = server().await;
+        let resp = get(&addr, "/malformed_chunked").await.unwrap();
+        assert!(resp.contains("transfer-encoding: chunked\r\n"));
+        assert!(resp.ends_with("\r\n\r\n6\r\nhello\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_hang_never_responds() {
+        let addr = server().await;
+        let resp = tokio::time::timeout(Duration::from_millis(200), get(&addr, "/hang")).await;
+        assert!(resp.is_err());
+    }
+}
@@ -1,46 +1,97 @@
-use std::{fs, path::PathBuf};
+use std::path::PathBuf;
 
-use axum::{middleware, Extension};
+use axum::{middleware, Extension, Router};
+use tokio::task::JoinSet;
 
-use crate::{delay_response, routing::config::Config};
+use crate::admin;
+use crate::journal::{self, Journal};
+use crate::mocks::Mocks;
+use crate::server;
+use crate::state::SharedState;
+use crate::validate::{self, Severity};
+use crate::watch;
 
-pub async fn start(config: PathBuf) {
-    let config = fs::read_to_string(config).expect("Unable to read config");
-    let config: Config = serde_yaml::from_str(&config).unwrap();
-    let options = config.clone().options;
-    let router = config
-        .router()
-        .layer(middleware::from_fn_with_state(
-            options.clone(),
-            delay_response,
-        ))
-        .layer(Extension(options.clone()));
-
-    match tokio::net::TcpListener::bind(format!(
-        "{}:{}",
-        options.clone().address,
-        options.clone().port
-    ))
-    .await
-    {
-        Ok(listener) => {
-            log::info!(
-                "Starting server on: {}:{}",
-                options.clone().address,
-                options.clone().port
-            );
-
-            if let Err(e) = axum::serve(listener, router).await {
-                log::error!("Failed to start server - {e}");
-            }
+pub async fn start(path: PathBuf, watch: bool) {
+    let validation = validate::validate_file(&path);
+    for diagnostic in &validation.diagnostics {
+        match diagnostic.severity {
+            Severity::Error => log::error!("{}", diagnostic.report(&path)),
+            Severity::Warning => log::warn!("{}", diagnostic.report(&path)),
         }
+    }
+    let config = match validation.config {
+        Some(config) if !validation.has_errors() => config,
+        _ => std::process::exit(1),
+    };
+    // all listeners share scenarios and the journal
+    let shared = SharedState::new(config.options.seed);
+    let journal = config
+        .options
+        .journal_capacity
+        .map_or_else(Journal::default, Journal::with_capacity);
+    let mut mocks = Vec::new();
+    let mut servers = JoinSet::new();
+    let listeners = match config.listeners() {
+        Ok(listeners) => listeners,
         Err(e) => {
-            log::error!(
-                "Failed to bind to: {}:{} - {e}",
-                options.clone().address,
-                options.clone().port
-            );
+            log::error!("{e}");
             std::process::exit(1);
         }
+    };
+    for listener in listeners {
+        let options = listener.options.clone();
+        let hosts = Mocks::hosts(&listener);
+        let listener_mocks = match Mocks::with_shared(listener, shared.clone()) {
+            Ok(mocks) => mocks,
+            Err(e) => {
+                log::error!("{e}");
+                std::process::exit(1);
+            }
+        };
+        mocks.push(listener_mocks.clone());
+        let router = app(listener_mocks, journal.clone());
+        let acceptor = match &options.tls {
+            Some(tls) => match tls.acceptor(&options.address, &hosts) {
+                Ok(acceptor) => Some(acceptor),
+                Err(e) => {
+                    log::error!("{e}");
+                    std::process::exit(1);
+                }
+            },
+            None => None,
+        };
+        match tokio::net::TcpListener::bind(format!("{}:{}", options.address, options.port)).await {
+            Ok(listener) => {
+                log::info!("Starting server on: {}:{}", options.address, options.port);
+                servers.spawn(server::serve(listener, router, acceptor));
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to bind to: {}:{} - {e}",
+                    options.address,
+                    options.port
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if watch {
+        if let Err(e) = watch::watch(path.clone(), &config, mocks) {
+            log::error!("Unable to watch {} - {e}", path.display());
+        }
     }
+    while let Some(result) = servers.join_next().await {
+        if let Ok(Err(e)) = result {
+            log::error!("Failed to start server - {e}");
+        }
+    }
+}
+
+pub fn app(mocks: Mocks, journal: Journal) -> Router {
+    let handler = mocks.clone();
+    let mocks_router = Router::new()
+        .fallback(move |req| handler.clone().handle(req))
+        .layer(middleware::from_fn(journal::record_request))
+        .layer(Extension(journal.clone()));
+    admin::router(journal, mocks).merge(mocks_router)
 }
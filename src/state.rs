@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::scenarios::Scenarios;
+
+/// State that is shared by all routes and survives changes of the config.
+#[derive(Clone, Debug)]
+pub struct SharedState {
+    pub scenarios: Scenarios,
+    pub calls: Calls,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl SharedState {
+    /// Random choices are reproducible if a seed is given.
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            scenarios: Scenarios::default(),
+            calls: Calls::default(),
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    pub fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap()
+    }
+}
+
+impl Default for SharedState {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Counts how often each condition responded, used to step through `responses`.
+#[derive(Clone, Debug, Default)]
+pub struct Calls {
+    counts: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Calls {
+    /// Returns the number of previous calls for the key and counts this one.
+    pub fn next(&self, key: &str) -> usize {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(key.to_string()).or_default();
+        let previous = *count;
+        *count += 1;
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::Calls;
+
+    #[test]
+    fn test_calls_are_counted_per_key() {
+        let calls = Calls::default();
+        assert_eq!(calls.next("a"), 0);
+        assert_eq!(calls.next("a"), 1);
+        assert_eq!(calls.next("b"), 0);
+    }
+}
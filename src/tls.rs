@@ -0,0 +1,517 @@
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+use rcgen::{
+    BasicConstraints, CertificateParams, CidrSubnet, DnType, GeneralSubtree, IsCa, Issuer, KeyPair,
+    NameConstraints,
+};
+use ring::digest;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{VerifierBuilderError, WebPkiClientVerifier};
+use rustls::{RootCertStore, ServerConfig};
+use serde::{Deserialize, Serialize};
+use tokio_rustls::TlsAcceptor;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+const DEFAULT_CA: &str = "mocked-ca.pem";
+
+/// Serves HTTPS using either the given certificate or a self-signed one.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Tls {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub self_signed: bool,
+    /// Where the CA of a self-signed certificate is written to. Its private
+    /// key is written next to it with the extension `.key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca: Option<PathBuf>,
+    /// Client certificates signed by this CA are accepted. Clients without a
+    /// certificate can still connect unless a route requires one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<PathBuf>,
+}
+
+/// The certificate a client presented during the handshake.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientCert {
+    pub common_name: Option<String>,
+    pub sans: Vec<String>,
+    /// The hex encoded SHA-256 of the certificate.
+    pub fingerprint: String,
+}
+
+impl ClientCert {
+    pub fn from_der(der: &[u8]) -> Option<ClientCert> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(String::from);
+        let sans = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name)
+                    | GeneralName::URI(name)
+                    | GeneralName::RFC822Name(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(ip) => ip_address(ip),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let fingerprint = digest::digest(&digest::SHA256, der)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Some(ClientCert {
+            common_name,
+            sans,
+            fingerprint,
+        })
+    }
+}
+
+fn ip_address(bytes: &[u8]) -> Option<String> {
+    match bytes.len() {
+        4 => <[u8; 4]>::try_from(bytes)
+            .ok()
+            .map(|ip| Ipv4Addr::from(ip).to_string()),
+        16 => <[u8; 16]>::try_from(bytes)
+            .ok()
+            .map(|ip| Ipv6Addr::from(ip).to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(PathBuf, io::Error),
+    Pem(PathBuf, rustls::pki_types::pem::Error),
+    Generate(rcgen::Error),
+    Config(rustls::Error),
+    ClientCa(PathBuf, VerifierBuilderError),
+    MissingCertificate,
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(path, e) => write!(f, "Unable to access {} - {e}", path.display()),
+            TlsError::Pem(path, e) => write!(f, "Invalid PEM file {} - {e}", path.display()),
+            TlsError::Generate(e) => write!(f, "Failed to generate certificate - {e}"),
+            TlsError::Config(e) => write!(f, "Invalid TLS config - {e}"),
+            TlsError::ClientCa(path, e) => {
+                write!(f, "Invalid client CA {} - {e}", path.display())
+            }
+            TlsError::MissingCertificate => {
+                write!(f, "TLS requires either cert and key or self_signed: true")
+            }
+        }
+    }
+}
+
+type Identity = (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>);
+
+impl Tls {
+    /// A self-signed certificate is issued for the address, `localhost` and
+    /// the `hosts` routes are restricted to.
+    pub fn acceptor(&self, address: &str, hosts: &[String]) -> Result<TlsAcceptor, TlsError> {
+        let (certs, key) = match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => Self::load(cert, key)?,
+            _ if self.self_signed => {
+                let ca = self.ca.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CA));
+                Self::generate(&names(address, hosts), &ca)?
+            }
+            _ => return Err(TlsError::MissingCertificate),
+        };
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(TlsError::Config)?;
+        let builder = match &self.client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in Self::load_certs(client_ca)? {
+                    roots.add(cert).map_err(TlsError::Config)?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider)
+                    .allow_unauthenticated()
+                    .build()
+                    .map_err(|e| TlsError::ClientCa(client_ca.clone(), e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .map_err(TlsError::Config)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    fn load(cert: &Path, key: &Path) -> Result<Identity, TlsError> {
+        let certs = Self::load_certs(cert)?;
+        let pem = fs::read(key).map_err(|e| TlsError::Io(key.to_path_buf(), e))?;
+        let key =
+            PrivateKeyDer::from_pem_slice(&pem).map_err(|e| TlsError::Pem(key.to_path_buf(), e))?;
+        Ok((certs, key))
+    }
+
+    fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+        let pem = fs::read(path).map_err(|e| TlsError::Io(path.to_path_buf(), e))?;
+        CertificateDer::pem_slice_iter(&pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| TlsError::Pem(path.to_path_buf(), e))
+    }
+
+    /// Creates a certificate for the names signed by the CA of [`Tls::issuer`].
+    fn generate(names: &[String], ca: &Path) -> Result<Identity, TlsError> {
+        let (issuer, ca_cert) = Self::issuer(ca, names)?;
+        let mut params = CertificateParams::new(names.to_vec()).map_err(TlsError::Generate)?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, names[0].as_str());
+        let key = KeyPair::generate().map_err(TlsError::Generate)?;
+        let cert = params
+            .signed_by(&key, &issuer)
+            .map_err(TlsError::Generate)?;
+        let key = PrivateKeyDer::Pkcs8(key.serialize_der().into());
+        Ok((vec![cert.der().clone(), ca_cert], key))
+    }
+
+    /// The CA that signs self-signed certificates. Its key is kept next to it
+    /// with the extension `.key`, so all listeners and later runs reuse it and
+    /// clients only have to trust it once.
+    ///
+    /// The CA is only valid for the names certificates were issued for. A CA
+    /// that does not cover all `names` is issued again for them, with the same
+    /// key, so certificates it signed before stay valid.
+    fn issuer(
+        ca: &Path,
+        names: &[String],
+    ) -> Result<(Issuer<'static, KeyPair>, CertificateDer<'static>), TlsError> {
+        let key_path = ca.with_extension("key");
+        let existing = key_path.is_file();
+        let key = if existing {
+            let pem =
+                fs::read_to_string(&key_path).map_err(|e| TlsError::Io(key_path.clone(), e))?;
+            KeyPair::from_pem(&pem).map_err(TlsError::Generate)?
+        } else {
+            let key = KeyPair::generate().map_err(TlsError::Generate)?;
+            write_private(&key_path, &key.serialize_pem())
+                .map_err(|e| TlsError::Io(key_path.clone(), e))?;
+            key
+        };
+        let mut permitted = Vec::new();
+        if existing && ca.is_file() {
+            let pem = fs::read(ca).map_err(|e| TlsError::Io(ca.to_path_buf(), e))?;
+            let cert = CertificateDer::from_pem_slice(&pem)
+                .map_err(|e| TlsError::Pem(ca.to_path_buf(), e))?;
+            permitted = permitted_names(&cert);
+            if names.iter().all(|name| permitted.contains(name)) {
+                let issuer = Issuer::from_ca_cert_der(&cert, key).map_err(TlsError::Generate)?;
+                log::info!("Signing the self-signed certificate with {}", ca.display());
+                return Ok((issuer, cert));
+            }
+        }
+        permitted.extend(names.iter().cloned());
+        permitted.sort();
+        permitted.dedup();
+        let mut params = CertificateParams::new(Vec::new()).map_err(TlsError::Generate)?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.name_constraints = Some(NameConstraints {
+            permitted_subtrees: permitted.iter().map(|name| subtree(name)).collect(),
+            excluded_subtrees: Vec::new(),
+        });
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "mocked CA");
+        let cert = params.self_signed(&key).map_err(TlsError::Generate)?;
+        fs::write(ca, cert.pem()).map_err(|e| TlsError::Io(ca.to_path_buf(), e))?;
+        log::info!(
+            "Wrote CA of the self-signed certificate for {} to {}",
+            permitted.join(", "),
+            ca.display()
+        );
+        Ok((Issuer::new(params, key), cert.der().clone()))
+    }
+}
+
+/// The names of a self-signed certificate, the address first. IP addresses
+/// are written the way [`permitted_names`] reads them back.
+fn names(address: &str, hosts: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in [address, "localhost"]
+        .into_iter()
+        .chain(hosts.iter().map(String::as_str))
+    {
+        let name = match name.parse::<IpAddr>() {
+            Ok(ip) => ip.to_string(),
+            Err(_) => name.to_ascii_lowercase(),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn subtree(name: &str) -> GeneralSubtree {
+    match name.parse::<IpAddr>() {
+        Ok(ip @ IpAddr::V4(_)) => GeneralSubtree::IpAddress(CidrSubnet::from_addr_prefix(ip, 32)),
+        Ok(ip @ IpAddr::V6(_)) => GeneralSubtree::IpAddress(CidrSubnet::from_addr_prefix(ip, 128)),
+        Err(_) => GeneralSubtree::DnsName(name.to_string()),
+    }
+}
+
+/// The names a CA is restricted to. A CA without name constraints is treated
+/// as restricted to none, so it is issued again with them.
+fn permitted_names(cert: &[u8]) -> Vec<String> {
+    let Ok((_, cert)) = X509Certificate::from_der(cert) else {
+        return Vec::new();
+    };
+    let Ok(Some(constraints)) = cert.name_constraints() else {
+        return Vec::new();
+    };
+    constraints
+        .value
+        .permitted_subtrees
+        .iter()
+        .flatten()
+        .filter_map(|subtree| match &subtree.base {
+            GeneralName::DNSName(name) => Some(name.to_string()),
+            // the address followed by the mask
+            GeneralName::IPAddress(ip) => ip_address(&ip[..ip.len() / 2]),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Private keys are only readable by the owner.
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair};
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::RootCertStore;
+    use tokio::net::TcpListener;
+
+    use crate::routing::config::Config;
+    use crate::server::serve;
+    use crate::state::SharedState;
+    use crate::tls::{permitted_names, Tls, TlsError};
+
+    #[test]
+    fn test_tls_requires_certificate() {
+        let result = Tls::default().acceptor("localhost", &[]);
+        assert!(matches!(result, Err(TlsError::MissingCertificate)));
+    }
+
+    #[tokio::test]
+    async fn test_self_signed_certificate_is_trusted_via_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = dir.path().join("ca.pem");
+        let tls = Tls {
+            self_signed: true,
+            ca: Some(ca.clone()),
+            ..Default::default()
+        };
+        tls.acceptor("localhost", &[]).unwrap();
+        let written = std::fs::read(&ca).unwrap();
+        // further listeners sign with the same CA
+        let acceptor = tls.acceptor("localhost", &[]).unwrap();
+        assert_eq!(std::fs::read(&ca).unwrap(), written);
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /secure
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+                      body: secure
+            "#,
+        )
+        .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let router = config.router(&SharedState::default()).unwrap();
+        tokio::spawn(serve(listener, router, Some(acceptor)));
+
+        let ca = reqwest::Certificate::from_pem(&std::fs::read(ca).unwrap()).unwrap();
+        let client = reqwest::Client::builder()
+            .add_root_certificate(ca)
+            .build()
+            .unwrap();
+        let resp = client
+            .get(format!("https://localhost:{port}/secure"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "secure");
+    }
+
+    /// Verifies a certificate for `name` signed with the key of the CA.
+    fn verify_signed_by_ca(ca: &Path, name: &str) -> Result<(), rustls::Error> {
+        let pem = std::fs::read(ca).unwrap();
+        let ca_cert = CertificateDer::from_pem_slice(&pem).unwrap();
+        let key = std::fs::read_to_string(ca.with_extension("key")).unwrap();
+        let issuer = Issuer::from_ca_cert_der(&ca_cert, KeyPair::from_pem(&key).unwrap()).unwrap();
+        let params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        let cert = params
+            .signed_by(&KeyPair::generate().unwrap(), &issuer)
+            .unwrap();
+        let mut roots = RootCertStore::empty();
+        roots.add(ca_cert).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier = WebPkiServerVerifier::builder_with_provider(roots.into(), provider)
+            .build()
+            .unwrap();
+        let name = ServerName::try_from(name.to_string()).unwrap();
+        verifier
+            .verify_server_cert(cert.der(), &[], &name, &[], UnixTime::now())
+            .map(|_| ())
+    }
+
+    #[test]
+    fn test_ca_is_restricted_to_its_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = dir.path().join("ca.pem");
+        let tls = Tls {
+            self_signed: true,
+            ca: Some(ca.clone()),
+            ..Default::default()
+        };
+        tls.acceptor("localhost", &[]).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(ca.with_extension("key")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+        assert!(verify_signed_by_ca(&ca, "localhost").is_ok());
+        assert!(verify_signed_by_ca(&ca, "example.com").is_err());
+
+        // further names widen the CA, keeping its key
+        let key = std::fs::read(ca.with_extension("key")).unwrap();
+        let hosts = [String::from("Users.local")];
+        tls.acceptor("127.0.0.1", &hosts).unwrap();
+        assert_eq!(std::fs::read(ca.with_extension("key")).unwrap(), key);
+        let pem = std::fs::read(&ca).unwrap();
+        let mut names = permitted_names(&CertificateDer::from_pem_slice(&pem).unwrap());
+        names.sort();
+        assert_eq!(names, ["127.0.0.1", "localhost", "users.local"]);
+        assert!(verify_signed_by_ca(&ca, "users.local").is_ok());
+        assert!(verify_signed_by_ca(&ca, "example.com").is_err());
+    }
+
+    fn client(ca: &Path, identity: Option<reqwest::Identity>) -> reqwest::Client {
+        let ca = reqwest::Certificate::from_pem(&std::fs::read(ca).unwrap()).unwrap();
+        let builder = reqwest::Client::builder().add_root_certificate(ca);
+        match identity {
+            Some(identity) => builder.identity(identity),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    }
+
+    fn identity(issuer: &Issuer<'_, KeyPair>, common_name: &str) -> reqwest::Identity {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.signed_by(&key, issuer).unwrap();
+        let pem = format!("{}{}", key.serialize_pem(), cert.pem());
+        reqwest::Identity::from_pem(pem.as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_cert_is_required_and_matched() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = dir.path().join("ca.pem");
+        let client_ca = dir.path().join("client-ca.pem");
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        std::fs::write(&client_ca, params.self_signed(&key).unwrap().pem()).unwrap();
+        let issuer = Issuer::new(params, key);
+
+        let tls = Tls {
+            self_signed: true,
+            ca: Some(ca.clone()),
+            client_ca: Some(client_ca),
+            ..Default::default()
+        };
+        let acceptor = tls.acceptor("localhost", &[]).unwrap();
+        let config: Config = serde_yaml::from_str(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: /orders
+                methods:
+                  - GET
+                require_client_cert: true
+                conditions:
+                  - type: ClientCert
+                    with:
+                      common_names:
+                        - orders
+                    response:
+                      status: 200
+                      body: welcome
+                  - response:
+                      status: 403
+            "#,
+        )
+        .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let router = config.router(&SharedState::default()).unwrap();
+        tokio::spawn(serve(listener, router, Some(acceptor)));
+        let url = format!("https://localhost:{port}/orders");
+
+        let resp = client(&ca, None).get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), 403);
+        assert_eq!(resp.text().await.unwrap(), "Client certificate required");
+        let orders = client(&ca, Some(identity(&issuer, "orders")));
+        let resp = orders.get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let billing = client(&ca, Some(identity(&issuer, "billing")));
+        let resp = billing.get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), 403);
+    }
+}
@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fmt, fs};
+
+use axum::http::{HeaderName, HeaderValue};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use crate::admin::ADMIN_PREFIX;
+use crate::mocks::{Mocks, RouteError};
+use crate::routing::body::Body;
+use crate::routing::condition::Condition;
+use crate::routing::config::Config;
+use crate::routing::format::Format;
+use crate::routing::method::Method;
+use crate::routing::response::{Response, ResponseError, STATUSES};
+use crate::routing::route::{check_path, Route};
+
+/// The fields of a condition. It flattens its matchers, so unknown fields are
+/// not rejected while parsing and e.g. a misspelled `type` makes it match
+/// every request.
+const CONDITION_FIELDS: &[&str] = &[
+    "type",
+    "with",
+    "and",
+    "or",
+    "scenario",
+    "state",
+    "response",
+    "responses",
+    "mode",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where in the config the problem is, e.g. `routes[0].conditions[1]`.
+    pub path: String,
+    pub message: String,
+    /// Line and column, both starting at 1.
+    pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}", self.message)?;
+        if !self.path.is_empty() {
+            write!(f, " ({})", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Diagnostic {
+    /// Formats the diagnostic as `file:line:column: severity: message`.
+    pub fn report(&self, file: &Path) -> String {
+        match self.location {
+            Some((line, column)) => format!("{}:{line}:{column}: {self}", file.display()),
+            None => format!("{}: {self}", file.display()),
+        }
+    }
+}
+
+pub struct Validation {
+    /// `None` if the config could not be parsed at all.
+    pub config: Option<Config>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    pub fn has_errors(&self) -> bool {
+        self.config.is_none()
+            || self
+                .diagnostics
+                .iter()
+                .any(|d| d.severity == Severity::Error)
+    }
+}
+
+pub fn validate_file(path: &Path) -> Validation {
+    match fs::read_to_string(path) {
+        Ok(source) => validate_as(&source, Format::from_path(path)),
+        Err(e) => Validation {
+            config: None,
+            diagnostics: vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                message: format!("Unable to read config - {e}"),
+                location: None,
+            }],
+        },
+    }
+}
+
+pub fn validate_as(source: &str, format: Format) -> Validation {
+    let config: Config = match format.parse(source) {
+        Ok(config) => config,
+        Err(e) => {
+            return Validation {
+                config: None,
+                diagnostics: vec![Diagnostic {
+                    severity: Severity::Error,
+                    path: String::new(),
+                    message: format!("Failed to parse config - {e}"),
+                    location: e.location(source),
+                }],
+            }
+        }
+    };
+    // the checks look at the raw document, whatever format it was written in
+    let raw: serde_yaml::Value = format.parse(source).unwrap_or_default();
+    let locations = match format {
+        // JSON documents are YAML documents, too
+        Format::Yaml | Format::Json => Locations::parse(source),
+        Format::Toml => Locations(HashMap::new()),
+    };
+    let mut checker = Checker {
+        locations,
+        diagnostics: Vec::new(),
+    };
+    checker.config(&config, &raw);
+    Validation {
+        config: Some(config),
+        diagnostics: checker.diagnostics,
+    }
+}
+
+struct Checker {
+    locations: Locations,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn add(&mut self, severity: Severity, path: String, message: String) {
+        let location = self.locations.find(&path);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path,
+            message,
+            location,
+        });
+    }
+
+    fn error(&mut self, path: String, message: String) {
+        self.add(Severity::Error, path, message);
+    }
+
+    fn config(&mut self, config: &Config, raw: &serde_yaml::Value) {
+        // routes with the address and port they are served on
+        let mut served = Vec::new();
+        for (index, route) in config.routes.iter().enumerate() {
+            let path = format!("routes[{index}]");
+            self.route(&path, route, &raw["routes"][index]);
+            let listener = (config.options.address.clone(), config.options.port);
+            served.push((path, listener, route.clone()));
+        }
+        for (index, server) in config.servers.iter().enumerate() {
+            let address = server.address.as_ref().unwrap_or(&config.options.address);
+            let port = server.port.unwrap_or(config.options.port);
+            for (route_index, route) in server.routes.iter().enumerate() {
+                let path = format!("servers[{index}].routes[{route_index}]");
+                self.route(&path, route, &raw["servers"][index]["routes"][route_index]);
+                let mut route = route.clone();
+                if route.hosts.is_empty() {
+                    route.hosts = server.hosts.clone();
+                }
+                served.push((path, (address.clone(), port), route));
+            }
+        }
+        for (index, (path, listener, route)) in served.iter().enumerate() {
+            let route = config.inherit_enable_cors_for(route.clone());
+            self.methods(path, &route);
+            let conflict = served[..index]
+                .iter()
+                .filter(|(_, other_listener, _)| other_listener == listener)
+                .find_map(|(other_path, _, other)| {
+                    let other = config.inherit_enable_cors_for(other.clone());
+                    route.conflicts_with(&other).map(|m| (other_path, m))
+                });
+            if let Some((other_path, method)) = conflict {
+                self.error(
+                    format!("{path}.path"),
+                    format!("{method} {} conflicts with {other_path}", route.path),
+                );
+            }
+        }
+        self.paths(config, &served);
+        if let Err(RouteError::SharedTls { server, .. }) = config.listeners() {
+            self.error(
+                format!("servers[{server}].tls"),
+                String::from("Servers sharing a port cannot set another tls than that port"),
+            );
+        }
+    }
+
+    /// Runs the check of the router on the routes of every listener and host,
+    /// which refuses paths that only differ in the names of their params.
+    fn paths(&mut self, config: &Config, served: &[(String, (String, u16), Route)]) {
+        let mut listeners: Vec<&(String, u16)> = served.iter().map(|(_, l, _)| l).collect();
+        listeners.sort();
+        listeners.dedup();
+        for listener in listeners {
+            let routes: Vec<&(String, (String, u16), Route)> =
+                served.iter().filter(|(_, l, _)| l == listener).collect();
+            let listener_config = Config {
+                routes: routes.iter().map(|(_, _, r)| r.clone()).collect(),
+                ..config.clone()
+            };
+            let mut groups = vec![routes
+                .iter()
+                .filter(|(_, _, r)| r.hosts.is_empty())
+                .copied()
+                .collect::<Vec<_>>()];
+            for host in Mocks::hosts(&listener_config) {
+                groups.push(
+                    routes
+                        .iter()
+                        .filter(|(_, _, r)| r.serves_host(&host))
+                        .copied()
+                        .collect(),
+                );
+            }
+            for group in groups {
+                if let Err((index, error)) = Mocks::check_paths(group.iter().map(|(_, _, r)| r)) {
+                    let path = format!("{}.path", group[index].0);
+                    // a conflict of the same path was reported already
+                    if !self.diagnostics.iter().any(|d| d.path == path) {
+                        self.error(path, error.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn route(&mut self, path: &str, route: &Route, raw: &serde_yaml::Value) {
+        if let Some(message) = check_path(&route.path) {
+            self.error(format!("{path}.path"), message);
+        }
+        if route.path == ADMIN_PREFIX || route.path.starts_with(&format!("{ADMIN_PREFIX}/")) {
+            self.add(
+                Severity::Warning,
+                format!("{path}.path"),
+                format!(
+                    "{ADMIN_PREFIX} is reserved for the admin API, which may shadow this route"
+                ),
+            );
+        }
+        let mut catch_all = None;
+        for (index, condition) in route.conditions.iter().enumerate() {
+            let condition_path = format!("{path}.conditions[{index}]");
+            self.condition(&condition_path, condition, &raw["conditions"][index]);
+            match &catch_all {
+                Some(catch_all) => self.add(
+                    Severity::Warning,
+                    condition_path,
+                    format!("Unreachable, {catch_all} matches every request"),
+                ),
+                None if matches_every_request(condition) => catch_all = Some(condition_path),
+                None => {}
+            }
+        }
+    }
+
+    /// Takes the route with the CORS setting it inherits from the options.
+    fn methods(&mut self, path: &str, route: &Route) {
+        if let Some((index, method)) = route.duplicate_method() {
+            self.error(
+                format!("{path}.methods[{index}]"),
+                format!("Method {method} is listed twice"),
+            );
+        }
+        if route.enable_cors.unwrap_or(false) {
+            if let Some(index) = route.methods.iter().position(|m| *m == Method::Options) {
+                self.error(
+                    format!("{path}.methods[{index}]"),
+                    String::from("OPTIONS cannot be mocked with CORS enabled"),
+                );
+            }
+        }
+    }
+
+    fn condition(&mut self, path: &str, condition: &Condition, raw: &serde_yaml::Value) {
+        if let Some(fields) = raw.as_mapping() {
+            for key in fields.keys() {
+                let key = key.as_str().unwrap_or_default();
+                if !CONDITION_FIELDS.contains(&key) {
+                    self.error(format!("{path}.{key}"), format!("Unknown field `{key}`"));
+                }
+            }
+        }
+        if condition.response.is_none() && condition.responses.is_empty() {
+            self.error(path.to_string(), String::from("Condition has no response"));
+        }
+        if let Some(response) = &condition.response {
+            self.response(&format!("{path}.response"), response);
+        }
+        for (index, response) in condition.responses.iter().enumerate() {
+            self.response(&format!("{path}.responses[{index}]"), response);
+        }
+    }
+
+    fn response(&mut self, path: &str, response: &Response) {
+        if !STATUSES.contains(&response.status) {
+            self.error(
+                format!("{path}.status"),
+                ResponseError::InvalidStatus(response.status).to_string(),
+            );
+        }
+        for (name, value) in &response.headers {
+            let header_path = format!("{path}.headers.{name}");
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                self.error(header_path.clone(), format!("Invalid header name `{name}`"));
+            }
+            if HeaderValue::from_str(value).is_err() {
+                self.error(header_path, format!("Invalid value of header `{name}`"));
+            }
+        }
+        if let Some(Body::Include(include)) = &response.body {
+            if !include.include.is_file() {
+                self.error(
+                    format!("{path}.body.include"),
+                    format!("Included file {} does not exist", include.include.display()),
+                );
+            }
+        }
+    }
+}
+
+fn matches_every_request(condition: &Condition) -> bool {
+    condition.matcher.is_none()
+        && condition.matchers.is_none()
+        && (condition.scenario.is_none() || condition.state.is_none())
+}
+
+/// The positions of all nodes of a YAML document by their path.
+struct Locations(HashMap<String, (usize, usize)>);
+
+enum Frame {
+    Sequence { path: String, index: usize },
+    Mapping { path: String, key: Option<String> },
+}
+
+struct Recorder {
+    stack: Vec<Frame>,
+    locations: HashMap<String, (usize, usize)>,
+}
+
+impl Recorder {
+    fn child_path(&self) -> String {
+        match self.stack.last() {
+            Some(Frame::Sequence { path, index }) => format!("{path}[{index}]"),
+            Some(Frame::Mapping { path, key }) => {
+                let key = key.as_deref().unwrap_or_default();
+                if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                }
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Moves on to the next entry of the current collection.
+    fn advance(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Sequence { index, .. }) => *index += 1,
+            Some(Frame::Mapping { key, .. }) => *key = None,
+            None => {}
+        }
+    }
+
+    fn record(&mut self, path: String, mark: Marker) {
+        self.locations
+            .entry(path)
+            .or_insert((mark.line(), mark.col() + 1));
+    }
+}
+
+impl MarkedEventReceiver for Recorder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let expects_key = matches!(self.stack.last(), Some(Frame::Mapping { key: None, .. }));
+        match event {
+            Event::Scalar(value, ..) if expects_key => {
+                if let Some(Frame::Mapping { path, key }) = self.stack.last_mut() {
+                    *key = Some(value);
+                    // the start of a block mapping is only known by its first key
+                    let path = path.clone();
+                    self.record(path, mark);
+                }
+                // entries point to their key
+                self.record(self.child_path(), mark);
+            }
+            Event::Scalar(..) | Event::Alias(_) => {
+                self.record(self.child_path(), mark);
+                self.advance();
+            }
+            Event::SequenceStart(..) => {
+                let path = self.child_path();
+                self.record(path.clone(), mark);
+                self.stack.push(Frame::Sequence { path, index: 0 });
+            }
+            Event::MappingStart(..) => {
+                let path = self.child_path();
+                self.stack.push(Frame::Mapping { path, key: None });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.stack.pop();
+                self.advance();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Locations {
+    fn parse(source: &str) -> Self {
+        let mut recorder = Recorder {
+            stack: Vec::new(),
+            locations: HashMap::new(),
+        };
+        // syntax errors are reported by serde_yaml already
+        let _ = Parser::new_from_str(source).load(&mut recorder, false);
+        Locations(recorder.locations)
+    }
+
+    /// The location of the path or of its closest parent.
+    fn find(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(location) = self.0.get(path) {
+                return Some(*location);
+            }
+            path = &path[..path.rfind(['.', '['])?];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routing::format::Format;
+    use crate::validate::{validate_as, Severity, Validation};
+
+    type Message = (Severity, String, Option<(usize, usize)>);
+
+    fn validate(source: &str) -> Validation {
+        validate_as(source, Format::Yaml)
+    }
+
+    fn messages(source: &str) -> Vec<Message> {
+        validate(source)
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.severity, d.message, d.location))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config_has_no_diagnostics() {
+        let validation = validate(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /v1/{id}
+    methods:
+      - GET
+    conditions:
+      - type: PathParam
+        with:
+          name: id
+          values:
+            - 1
+        response:
+          status: 200
+      - response:
+          status: 404
+"#,
+        );
+        assert!(!validation.has_errors());
+        assert!(validation.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors_have_location() {
+        let validation = validate("options:\n  address: localhost\n  port: abc\nroutes: []\n");
+        assert!(validation.has_errors());
+        assert_eq!(validation.diagnostics[0].location, Some((3, 9)));
+    }
+
+    #[test]
+    fn test_json_and_toml_are_checked() {
+        let json = r#"{
+  "options": {"address": "localhost", "port": 3003},
+  "routes": [
+    {"path": "v1", "methods": ["GET"], "conditions": [{"response": {"status": 200}}]}
+  ]
+}"#;
+        let validation = validate_as(json, Format::Json);
+        assert_eq!(validation.diagnostics.len(), 1);
+        assert_eq!(validation.diagnostics[0].location, Some((4, 6)));
+        let toml = r#"
+[options]
+address = "localhost"
+port = 3003
+
+[[routes]]
+path = "/v1"
+methods = ["GET"]
+
+[[routes.conditions]]
+stat = "paid"
+"#;
+        let messages: Vec<String> = validate_as(toml, Format::Toml)
+            .diagnostics
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                String::from("Unknown field `stat`"),
+                String::from("Condition has no response"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_the_router_cannot_tell_apart_are_reported() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /users/{id}
+    methods: [GET]
+    conditions:
+      - response:
+          status: 200
+  - path: /users/{name}
+    methods: [POST]
+    conditions:
+      - response:
+          status: 201
+servers:
+  - hosts: [admin.local]
+    routes:
+      - path: /users/{name}
+        methods: [GET]
+        conditions:
+          - response:
+              status: 200
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![(
+                Severity::Error,
+                String::from("Invalid path /users/{name} - Insertion failed due to conflict with previously registered route: /users/{id}"),
+                Some((11, 5)),
+            )]
+        );
+    }
+
+    #[test]
+    fn test_responses_are_checked() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /v1
+    methods:
+      - GET
+    conditions:
+      - response:
+          status: 1000
+          headers:
+            Content Type: text/plain
+          body:
+            include: does/not/exist.json
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    Severity::Error,
+                    String::from("Status 1000 is not between 100 and 599"),
+                    Some((11, 11))
+                ),
+                (
+                    Severity::Error,
+                    String::from("Invalid header name `Content Type`"),
+                    Some((13, 13))
+                ),
+                (
+                    Severity::Error,
+                    String::from("Included file does/not/exist.json does not exist"),
+                    Some((15, 13))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misspelled_fields_and_unreachable_conditions() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /v1
+    methods:
+      - GET
+    conditions:
+      - scenario: checkout
+        stat: paid
+        response:
+          status: 200
+      - respone:
+          status: 401
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    Severity::Error,
+                    String::from("Unknown field `stat`"),
+                    Some((11, 9))
+                ),
+                (
+                    Severity::Error,
+                    String::from("Unknown field `respone`"),
+                    Some((14, 9))
+                ),
+                (
+                    Severity::Error,
+                    String::from("Condition has no response"),
+                    Some((14, 9))
+                ),
+                (
+                    Severity::Warning,
+                    String::from("Unreachable, routes[0].conditions[0] matches every request"),
+                    Some((14, 9))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_are_checked() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /v1/:id
+    methods:
+      - GET
+    conditions: []
+  - path: /v2/{id}
+    methods:
+      - GET
+    conditions: []
+  - path: /v2/{name}
+    methods:
+      - GET
+    conditions: []
+servers:
+  - port: 3004
+    routes:
+      - path: /v2/{id}
+        methods:
+          - GET
+        conditions: []
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    Severity::Error,
+                    String::from("Path params are written as {id}, not :id"),
+                    Some((6, 5))
+                ),
+                (
+                    Severity::Error,
+                    String::from("GET /v2/{name} conflicts with routes[1]"),
+                    Some((14, 5))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_servers_sharing_a_port_keep_its_tls() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes: []
+servers:
+  - hosts: [secure.local]
+    tls:
+      self_signed: true
+    routes: []
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![(
+                Severity::Error,
+                String::from("Servers sharing a port cannot set another tls than that port"),
+                Some((8, 5))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_methods_are_reported() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /v1
+    methods:
+      - GET
+      - POST
+      - GET
+    conditions: []
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![(
+                Severity::Error,
+                String::from("Method GET is listed twice"),
+                Some((10, 9))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_options_with_inherited_cors_is_reported() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+  enable_cors: true
+routes:
+  - path: /v1
+    methods:
+      - GET
+      - OPTIONS
+    conditions: []
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![(
+                Severity::Error,
+                String::from("OPTIONS cannot be mocked with CORS enabled"),
+                Some((10, 9))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_routes_below_the_admin_prefix_are_warned_about() {
+        let diagnostics = messages(
+            r#"
+options:
+  address: localhost
+  port: 3003
+routes:
+  - path: /__mocked/health
+    methods:
+      - GET
+    conditions: []
+  - path: /__mockedup
+    methods:
+      - GET
+    conditions: []
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![(
+                Severity::Warning,
+                String::from(
+                    "/__mocked is reserved for the admin API, which may shadow this route"
+                ),
+                Some((6, 5))
+            )]
+        );
+    }
+}
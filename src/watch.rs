@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+use std::path::{self, Path, PathBuf};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::mocks::Mocks;
+use crate::routing::config::Config;
+use crate::validate::{self, Severity};
+
+/// Editors usually emit several events for a single save.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+struct Watched {
+    config: PathBuf,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Watched {
+    fn new(config: PathBuf) -> Self {
+        Self {
+            config,
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        }
+    }
+
+    /// Watches the directories of the config file and all includes instead of
+    /// the files themselves, so files that are replaced on save are still seen.
+    fn update(&mut self, watcher: &mut impl Watcher, config: &Config) {
+        let mut files: HashSet<PathBuf> = config
+            .includes()
+            .iter()
+            .filter_map(|p| absolute(p))
+            .collect();
+        files.insert(self.config.clone());
+        for file in &files {
+            let Some(dir) = file.parent() else {
+                continue;
+            };
+            if !self.dirs.contains(dir) {
+                match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                    Ok(()) => {
+                        self.dirs.insert(dir.to_path_buf());
+                    }
+                    Err(e) => log::warn!("Unable to watch {} - {e}", dir.display()),
+                }
+            }
+        }
+        self.files = files;
+    }
+
+    fn is_relevant(&self, event: &Event) -> bool {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|p| self.files.contains(p))
+    }
+}
+
+fn absolute(path: &Path) -> Option<PathBuf> {
+    path::absolute(path).ok()
+}
+
+/// Swaps in the routes of every listener. Nothing is changed unless all of
+/// them are valid.
+fn reload(config: &Config, mocks: &[Mocks]) -> Result<(), String> {
+    let listeners = config.listeners().map_err(|e| e.to_string())?;
+    let unchanged = listeners.len() == mocks.len()
+        && listeners.iter().zip(mocks).all(|(l, m)| {
+            let options = m.options();
+            l.options.address == options.address && l.options.port == options.port
+        });
+    if !unchanged {
+        return Err(String::from(
+            "Changing addresses or ports requires a restart",
+        ));
+    }
+    for listener in &listeners {
+        Mocks::validate(listener).map_err(|e| e.to_string())?;
+    }
+    for (listener, mocks) in listeners.into_iter().zip(mocks) {
+        mocks.replace_config(listener).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Watches the config file and every included file and swaps in the new
+/// config on change. If the new config is invalid, the old one is kept.
+pub fn watch(path: PathBuf, config: &Config, mocks: Vec<Mocks>) -> notify::Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) => {
+                let _ = tx.send(event);
+            }
+            Err(e) => log::warn!("Error while watching config - {e}"),
+        })?;
+    let config_path = absolute(&path).unwrap_or(path);
+    let mut watched = Watched::new(config_path.clone());
+    watched.update(&mut watcher, config);
+    log::info!("Watching {} for changes", config_path.display());
+
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if !watched.is_relevant(&event) {
+                continue;
+            }
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            let validation = validate::validate_file(&config_path);
+            match validation.config {
+                Some(config) if !validation.has_errors() => match reload(&config, &mocks) {
+                    Ok(()) => {
+                        log::info!("Reloaded {}", config_path.display());
+                        watched.update(&mut watcher, &config);
+                    }
+                    Err(e) => log::error!("Keeping previous config - {e}"),
+                },
+                _ => {
+                    validation
+                        .diagnostics
+                        .iter()
+                        .filter(|d| d.severity == Severity::Error)
+                        .for_each(|d| log::error!("{}", d.report(&config_path)));
+                    log::error!("Keeping previous config");
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use crate::mocks::Mocks;
+    use crate::routing::config::Config;
+    use crate::watch::watch;
+
+    fn config(path: &str) -> String {
+        format!(
+            r#"
+            options:
+              address: localhost
+              port: 3003
+            routes:
+              - path: {path}
+                methods:
+                  - GET
+                conditions:
+                  - response:
+                      status: 200
+            "#
+        )
+    }
+
+    async fn wait_for_path(mocks: &Mocks, path: &str) -> bool {
+        for _ in 0..50 {
+            if mocks.routes().iter().any(|r| r.path == path) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_config_is_reloaded_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mocked.yml");
+        fs::write(&path, config("/first")).unwrap();
+        let initial = Config::from_file(&path).unwrap();
+        let mocks = Mocks::new(initial.clone()).unwrap();
+        watch(path.clone(), &initial, vec![mocks.clone()]).unwrap();
+
+        fs::write(&path, config("/second")).unwrap();
+        assert!(wait_for_path(&mocks, "/second").await);
+
+        // an invalid config keeps the previous one
+        fs::write(&path, "routes: [").unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(mocks.routes().iter().any(|r| r.path == "/second"));
+
+        fs::write(&path, config("/third")).unwrap();
+        assert!(wait_for_path(&mocks, "/third").await);
+    }
+
+    #[tokio::test]
+    async fn test_config_with_indistinguishable_paths_is_not_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mocked.yml");
+        fs::write(&path, config("/first")).unwrap();
+        let initial = Config::from_file(&path).unwrap();
+        let mocks = Mocks::new(initial.clone()).unwrap();
+        watch(path.clone(), &initial, vec![mocks.clone()]).unwrap();
+
+        // the router cannot tell `/users/{id}` from `/users/{name}`
+        let conflicting = config("/users/{id}")
+            + r#"
+              - path: /users/{name}
+                methods:
+                  - POST
+                conditions:
+                  - response:
+                      status: 201
+            "#;
+        let parsed: Config = serde_yaml::from_str(&conflicting).unwrap();
+        assert!(Mocks::validate(&parsed).is_err());
+        fs::write(&path, conflicting).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(mocks.routes().iter().any(|r| r.path == "/first"));
+
+        fs::write(&path, config("/second")).unwrap();
+        assert!(wait_for_path(&mocks, "/second").await);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_survives_config_that_cannot_be_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mocked.yml");
+        fs::write(&path, config("/first")).unwrap();
+        let initial = Config::from_file(&path).unwrap();
+        let mocks = Mocks::new(initial.clone()).unwrap();
+        watch(path.clone(), &initial, vec![mocks.clone()]).unwrap();
+
+        // axum panics on both when building the router
+        let unservable = [
+            config("/twice").replace("- GET", "- GET\n                  - GET"),
+            config("/options")
+                .replace("- GET", "- OPTIONS")
+                .replace("port: 3003", "port: 3003\n              enable_cors: true"),
+        ];
+        for edit in unservable {
+            fs::write(&path, edit).unwrap();
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            assert!(mocks.routes().iter().any(|r| r.path == "/first"));
+        }
+
+        fs::write(&path, config("/second")).unwrap();
+        assert!(wait_for_path(&mocks, "/second").await);
+    }
+}